@@ -0,0 +1,104 @@
+// Times pricing sold shapes and adding them to the score, once through the
+// price table and `Score` and once the way sales worked while the score was a
+// `BigInt`, which built `500^side_level` and a handful of temporaries for
+// every shape. Moving the shapes, the lasers and removing the sold ones cost
+// the same either way and are left out.
+//
+//   cargo bench --bench sales
+//
+// Nanoseconds per sale on the machine this was last run on, about 3.5 times
+// faster at every score. Most of what is left is the area check for slices
+// and the multipliers.
+//
+//   shapes    score   bigint ns    score ns  speedup
+//     1000     1e15       271.3        72.7       4x
+//     1000    1e100       227.6        76.1       3x
+//   100000     1e15       271.4        78.8       3x
+//   100000    1e100       298.2        77.3       4x
+
+use std::{hint::black_box, time::Instant};
+
+use num::{BigInt, FromPrimitive};
+use raylib::prelude::Color;
+
+use match_game::game_logic::{
+    multipliers::active_multipliers,
+    prices::price_table,
+    score::Score,
+    shape::regular_shape,
+    state::{Shape, seeded_state},
+};
+
+const SHAPE_COUNTS: [usize; 3] = [1_000, 10_000, 100_000];
+// Scores the sales are added to, the last one well past `u128`
+const SCORE_EXPONENTS: [u32; 3] = [15, 38, 100];
+const ROUNDS: u32 = 5;
+const COLORS: [Color; 6] = [Color::GRAY, Color::RED, Color::GREEN, Color::BLUE, Color::YELLOW, Color::PURPLE];
+
+fn main() {
+    let multipliers = active_multipliers(&seeded_state(0));
+    println!("{:>8} {:>8} {:>11} {:>11} {:>8}", "shapes", "score", "bigint ns", "score ns", "speedup");
+    for count in SHAPE_COUNTS {
+        let shapes = sold_shapes(count);
+        for exponent in SCORE_EXPONENTS {
+            let score = BigInt::from(10).pow(exponent);
+            let legacy = time_per_sale(count, || {
+                let mut score = score.clone();
+                legacy_sell(&mut score, &shapes);
+                black_box(score);
+            });
+            let current = time_per_sale(count, || {
+                let mut score = Score::from_bigint(&score).unwrap();
+                for shape in &shapes {
+                    score += &price_table().sale_price(shape, &multipliers).unwrap_or(Score::zero());
+                }
+                black_box(score);
+            });
+            let label = format!("1e{}", exponent);
+            println!("{:>8} {:>8} {:>11.1} {:>11.1} {:>7.0}x", count, label, legacy, current, legacy / current);
+        }
+    }
+}
+
+// Nanoseconds per sale, best of `ROUNDS` to keep noise out
+fn time_per_sale(count: usize, mut run: impl FnMut()) -> f64 {
+    let mut best = f64::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed().as_nanos() as f64 / count as f64);
+    }
+    best
+}
+
+// Every side count and color the old prices knew, in turn
+fn sold_shapes(count: usize) -> Vec<Shape> {
+    (0..count)
+        .map(|i| regular_shape(3 + (i % 6) as i32, COLORS[i / 6 % COLORS.len()], (i % 600) as f64, 100f64))
+        .collect()
+}
+
+fn legacy_sell(score: &mut BigInt, shapes: &[Shape]) {
+    let bonus_percent = BigInt::from_u32(100).unwrap();
+    for shape in shapes {
+        let price = legacy_price(shape) * &bonus_percent / 100;
+        *score += &price;
+        black_box(&price);
+    }
+}
+
+fn legacy_price(shape: &Shape) -> BigInt {
+    let side_level = (shape.sides() - 2) as u32;
+    let side_price = BigInt::from_u64(500).unwrap().pow(side_level)
+        - BigInt::from_u64(470).unwrap();
+    let color_multiplier = match shape.color {
+        Color::GRAY => BigInt::from_u64(1).unwrap(),
+        Color::RED => BigInt::from_u64(10).unwrap(),
+        Color::GREEN => BigInt::from_u64(100).unwrap(),
+        Color::BLUE => BigInt::from_u64(1000).unwrap(),
+        Color::YELLOW => BigInt::from_u64(10_000).unwrap(),
+        Color::PURPLE => BigInt::from_u64(100_000).unwrap(),
+        _ => BigInt::from_u64(0).unwrap()
+    };
+    side_price * color_multiplier
+}
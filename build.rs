@@ -0,0 +1,28 @@
+use std::{env, fs, path::Path};
+
+// Generates the table of files compiled in by the `embed-assets` feature. The
+// table is always written so the include in assets.rs never dangles.
+fn main() {
+    println!("cargo:rerun-if-changed=assets");
+    println!("cargo:rerun-if-changed=build.rs");
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("embedded_assets.rs");
+    let mut table = String::from("pub const EMBEDDED_ASSETS: &[(&str, &[u8])] = &[\n");
+    if env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_some() {
+        let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("assets");
+        let manifest = fs::read_to_string(dir.join("manifest.txt")).unwrap();
+        for line in manifest.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let path = match line.split_once('=') {
+                Some((_, path)) => path.trim(),
+                None => continue,
+            };
+            let file = dir.join(path);
+            if !file.is_file() {
+                println!("cargo:warning=Not embedding missing asset {}", file.display());
+                continue;
+            }
+            table += &format!("    ({:?}, include_bytes!({:?})),\n", path, file);
+        }
+    }
+    table += "];\n";
+    fs::write(out, table).unwrap();
+}
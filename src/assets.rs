@@ -1,11 +1,332 @@
-use raylib::{RaylibHandle, texture::Texture2D, RaylibThread};
+use std::{collections::HashMap, fmt::Display, fs, path::PathBuf};
+#[cfg(not(feature = "embed-assets"))]
+use std::env;
+
+use raylib::{
+    RaylibHandle,
+    RaylibThread,
+    core::audio::{Music, Sound},
+    prelude::{Color, Rectangle},
+    text::Font,
+    texture::{Image, Texture2D},
+};
+
+// Overrides where the assets directory is looked up
+#[cfg(not(feature = "embed-assets"))]
+pub const ASSETS_DIR_ENV: &str = "GEOMETRIC_PLUMBER_ASSETS";
+#[cfg(not(feature = "embed-assets"))]
+const MANIFEST_FILE: &str = "manifest.txt";
+// Used when the manifest next to the assets is missing or unreadable, and
+// always with `embed-assets`
+const BUILT_IN_MANIFEST: &str = include_str!("../assets/manifest.txt");
+
+// `EMBEDDED_ASSETS`, pairs of manifest paths and file contents, see build.rs
+#[cfg(feature = "embed-assets")]
+include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+const ATLAS_WIDTH: i32 = 1024;
+// Gap between packed textures so filtering doesn't bleed into neighbours
+const ATLAS_PADDING: i32 = 2;
+const PLACEHOLDER_SIZE: i32 = 64;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AssetKind {
+    Texture,
+    Font,
+    Sound,
+    Music,
+    Data,
+}
+
+// Where the bytes of an asset come from
+#[derive(Clone, Debug)]
+pub enum AssetSource {
+    File(PathBuf),
+    // Compiled into the executable
+    #[cfg(feature = "embed-assets")]
+    Embedded { path: String, bytes: &'static [u8] },
+}
+
+impl AssetSource {
+    pub fn file(&self) -> Option<&PathBuf> {
+        match self {
+            AssetSource::File(path) => Some(path),
+            #[cfg(feature = "embed-assets")]
+            AssetSource::Embedded { .. } => None,
+        }
+    }
+}
+
+impl Display for AssetSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetSource::File(path) => write!(f, "{}", path.display()),
+            #[cfg(feature = "embed-assets")]
+            AssetSource::Embedded { path, .. } => write!(f, "embedded {}", path),
+        }
+    }
+}
+
+pub struct AssetEntry {
+    pub kind: AssetKind,
+    pub name: String,
+    pub source: AssetSource,
+}
 
 pub struct LoadedAssets {
-    pub pipe_texture: Texture2D
+    // Every texture packed into one, see `texture_region`
+    pub atlas: Texture2D,
+    pub regions: HashMap<String, Rectangle>,
+    pub fonts: HashMap<String, Font>,
+    // Sounds and music are opened by the audio backend with `load_sound` and `load_music`
+    pub sounds: HashMap<String, AssetSource>,
+    pub music: HashMap<String, AssetSource>,
+    // Text files, read with `read_data`
+    pub data: HashMap<String, AssetSource>,
+    // Everything that was read from disk, including the manifest
+    pub files: Vec<PathBuf>,
+}
+
+impl LoadedAssets {
+    // Unknown names get the placeholder so drawing never has to care
+    pub fn texture_region(&self, name: &str) -> Rectangle {
+        match self.regions.get(name) {
+            Some(region) => *region,
+            None => self.regions["placeholder"],
+        }
+    }
+}
+
+// The environment override wins, then an `assets` directory next to the
+// executable or up to a few levels above it (covers `target/<profile>`), and
+// finally the working directory
+#[cfg(not(feature = "embed-assets"))]
+pub fn assets_dir() -> PathBuf {
+    if let Ok(dir) = env::var(ASSETS_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    if let Ok(exe) = env::current_exe() {
+        let found = exe.ancestors()
+            .skip(1)
+            .take(4)
+            .map(|dir| dir.join("assets"))
+            .find(|dir| dir.join(MANIFEST_FILE).is_file());
+        if let Some(dir) = found {
+            return dir;
+        }
+    }
+    PathBuf::from("assets")
+}
+
+// Also returns the manifest file when it was read from disk
+#[cfg(not(feature = "embed-assets"))]
+fn manifest_entries() -> (Vec<AssetEntry>, Option<PathBuf>) {
+    let dir = assets_dir();
+    let path = dir.join(MANIFEST_FILE);
+    let (contents, manifest) = match fs::read_to_string(&path) {
+        Ok(contents) => (contents, Some(path)),
+        Err(e) => {
+            eprintln!("Warning: failed to read {}: {}, using the built in manifest", path.display(), e);
+            (BUILT_IN_MANIFEST.to_string(), None)
+        }
+    };
+    (parse_manifest(&contents, |path| AssetSource::File(dir.join(path))), manifest)
+}
+
+#[cfg(feature = "embed-assets")]
+fn manifest_entries() -> (Vec<AssetEntry>, Option<PathBuf>) {
+    // Files that were missing at build time are still looked for on disk
+    let entries = parse_manifest(BUILT_IN_MANIFEST, |path| {
+        match EMBEDDED_ASSETS.iter().find(|(embedded, _)| *embedded == path) {
+            Some((_, bytes)) => AssetSource::Embedded { path: path.to_string(), bytes },
+            None => AssetSource::File(PathBuf::from("assets").join(path)),
+        }
+    });
+    (entries, None)
+}
+
+fn parse_manifest(contents: &str, source: impl Fn(&str) -> AssetSource) -> Vec<AssetEntry> {
+    let mut entries = vec![];
+    for line in contents.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        match parse_manifest_line(line, &source) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Warning: ignoring manifest line '{}': {}", line, e),
+        }
+    }
+    entries
+}
+
+fn parse_manifest_line(line: &str, source: impl Fn(&str) -> AssetSource) -> Result<AssetEntry, String> {
+    let (key, path) = line.split_once('=').ok_or("expected kind name = path")?;
+    let (kind, name) = key.trim().split_once(' ').ok_or("expected kind name = path")?;
+    let kind = match kind {
+        "texture" => AssetKind::Texture,
+        "font" => AssetKind::Font,
+        "sound" => AssetKind::Sound,
+        "music" => AssetKind::Music,
+        "data" => AssetKind::Data,
+        _ => return Err(format!("unknown asset kind '{}'", kind)),
+    };
+    Ok(AssetEntry { kind, name: name.trim().to_string(), source: source(path.trim()) })
 }
 
 pub fn load_assets(rl: &mut RaylibHandle, thread: &RaylibThread) -> LoadedAssets {
-    LoadedAssets { 
-        pipe_texture: rl.load_texture(&thread, "assets/pipe.png").unwrap() 
+    let (entries, manifest) = manifest_entries();
+    let mut images = vec![("placeholder".to_string(), placeholder_image())];
+    let mut fonts = HashMap::new();
+    let mut sounds = HashMap::new();
+    let mut music = HashMap::new();
+    let mut data = HashMap::new();
+    let mut files: Vec<PathBuf> = manifest.into_iter().collect();
+    for entry in entries {
+        files.extend(entry.source.file().cloned());
+        match entry.kind {
+            AssetKind::Texture => {
+                let image = load_image(&entry.source).unwrap_or_else(|e| {
+                    eprintln!("Warning: failed to load texture {}: {}, using a placeholder", entry.source, e);
+                    placeholder_image()
+                });
+                images.push((entry.name, image));
+            },
+            AssetKind::Font => match load_font(rl, thread, &entry.source) {
+                Ok(font) => { fonts.insert(entry.name, font); },
+                Err(e) => eprintln!("Warning: failed to load font {}: {}, using the default font", entry.source, e),
+            },
+            AssetKind::Sound => { sounds.insert(entry.name, entry.source); },
+            AssetKind::Music => { music.insert(entry.name, entry.source); },
+            AssetKind::Data => { data.insert(entry.name, entry.source); },
+        }
+    }
+    let (atlas_image, regions) = pack_atlas(&images);
+    let atlas = rl.load_texture_from_image(thread, &atlas_image)
+        .expect("Failed to upload the texture atlas");
+    LoadedAssets { atlas, regions, fonts, sounds, music, data, files }
+}
+
+// Magenta checkers, hard to miss but nothing crashes
+fn placeholder_image() -> Image {
+    Image::gen_image_checked(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, 8, 8, Color::MAGENTA, Color::BLACK)
+}
+
+// Simple shelf packing: tallest images first, filling rows left to right
+fn pack_atlas(images: &[(String, Image)]) -> (Image, HashMap<String, Rectangle>) {
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|i| -images[*i].1.height());
+    let width = images.iter()
+        .map(|(_, image)| image.width() + ATLAS_PADDING)
+        .max()
+        .unwrap_or(0)
+        .max(ATLAS_WIDTH);
+    let mut positions = HashMap::new();
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for i in order {
+        let (name, image) = &images[i];
+        if x + image.width() > width {
+            x = 0;
+            y += shelf_height + ATLAS_PADDING;
+            shelf_height = 0;
+        }
+        positions.insert(name.clone(), (i, x, y));
+        x += image.width() + ATLAS_PADDING;
+        shelf_height = shelf_height.max(image.height());
+    }
+    let mut atlas = Image::gen_image_color(width, y + shelf_height, Color::BLANK);
+    let mut regions = HashMap::new();
+    for (name, (i, x, y)) in positions {
+        let image = &images[i].1;
+        let source = Rectangle::new(0f32, 0f32, image.width() as f32, image.height() as f32);
+        let region = Rectangle::new(x as f32, y as f32, image.width() as f32, image.height() as f32);
+        atlas.draw(image, source, region, Color::WHITE);
+        regions.insert(name, region);
+    }
+    (atlas, regions)
+}
+
+pub fn read_data(source: &AssetSource) -> Result<String, String> {
+    match source {
+        AssetSource::File(path) => fs::read_to_string(path).map_err(|e| e.to_string()),
+        #[cfg(feature = "embed-assets")]
+        AssetSource::Embedded { bytes, .. } => String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string()),
+    }
+}
+
+fn load_image(source: &AssetSource) -> Result<Image, String> {
+    match source {
+        AssetSource::File(path) => Image::load_image(&path.to_string_lossy()),
+        #[cfg(feature = "embed-assets")]
+        AssetSource::Embedded { path, bytes } => {
+            Image::load_image_from_mem(&file_type(path), &bytes.to_vec(), bytes.len() as i32)
+        },
+    }
+}
+
+fn load_font(rl: &mut RaylibHandle, thread: &RaylibThread, source: &AssetSource) -> Result<Font, String> {
+    match source {
+        AssetSource::File(path) => rl.load_font(thread, &path.to_string_lossy()),
+        #[cfg(feature = "embed-assets")]
+        AssetSource::Embedded { path, bytes } => {
+            let file_type = std::ffi::CString::new(file_type(path)).unwrap();
+            // Same size and default character set as `load_font`
+            let font = unsafe {
+                raylib::ffi::LoadFontFromMemory(
+                    file_type.as_ptr(),
+                    bytes.as_ptr(),
+                    bytes.len() as i32,
+                    32,
+                    std::ptr::null_mut(),
+                    0
+                )
+            };
+            if font.texture.id == 0 {
+                return Err("unsupported font data".to_string());
+            }
+            Ok(unsafe { Font::from_raw(font) })
+        },
+    }
+}
+
+pub fn load_sound(source: &AssetSource) -> Result<Sound, String> {
+    match source {
+        AssetSource::File(path) => Sound::load_sound(&path.to_string_lossy()),
+        #[cfg(feature = "embed-assets")]
+        AssetSource::Embedded { path, bytes } => {
+            let file_type = std::ffi::CString::new(file_type(path)).unwrap();
+            let wave = unsafe {
+                raylib::ffi::LoadWaveFromMemory(file_type.as_ptr(), bytes.as_ptr(), bytes.len() as i32)
+            };
+            if wave.data.is_null() {
+                return Err("unsupported sound data".to_string());
+            }
+            let wave = unsafe { raylib::core::audio::Wave::from_raw(wave) };
+            Sound::load_sound_from_wave(&wave)
+        },
     }
-}
\ No newline at end of file
+}
+
+pub fn load_music(thread: &RaylibThread, source: &AssetSource) -> Result<Music, String> {
+    match source {
+        AssetSource::File(path) => Music::load_music_stream(thread, &path.to_string_lossy()),
+        #[cfg(feature = "embed-assets")]
+        AssetSource::Embedded { path, bytes } => {
+            let file_type = std::ffi::CString::new(file_type(path)).unwrap();
+            // Streams straight from the embedded bytes, which live as long as the program.
+            // raylib takes a mutable pointer but only reads through it.
+            let music = unsafe {
+                raylib::ffi::LoadMusicStreamFromMemory(file_type.as_ptr(), bytes.as_ptr() as *mut u8, bytes.len() as i32)
+            };
+            if music.ctxData.is_null() {
+                return Err("unsupported music data".to_string());
+            }
+            Ok(unsafe { Music::from_raw(music) })
+        },
+    }
+}
+
+// raylib picks the decoder from the extension, dot included
+#[cfg(feature = "embed-assets")]
+fn file_type(path: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((_, extension)) => format!(".{}", extension),
+        None => String::new(),
+    }
+}
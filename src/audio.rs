@@ -0,0 +1,311 @@
+use std::collections::{HashMap, VecDeque};
+
+use raylib::{RaylibThread, core::audio::{Music, RaylibAudio, Sound}};
+
+use crate::{assets::{LoadedAssets, load_music, load_sound}, game_logic::{events::GameEvent, state::GameState}, settings::Settings};
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum SoundEffect {
+    Purchase,
+    PipeDrop,
+    LaserCut,
+    BigSale,
+}
+
+// Names of the sounds in the asset manifest
+const SOUND_NAMES: [(SoundEffect, &str); 4] = [
+    (SoundEffect::Purchase, "purchase"),
+    (SoundEffect::PipeDrop, "drop"),
+    (SoundEffect::LaserCut, "laser"),
+    (SoundEffect::BigSale, "sale"),
+];
+
+// Early game track, then the busier one once the factory grows
+const MUSIC_NAMES: [&str; 2] = ["calm", "busy"];
+
+// Thousands of shapes can drop or sell in a second. Each effect may only
+// restart after a short gap and only a handful start per second overall.
+const MIN_REPEAT_INTERVAL: f64 = 0.08;
+const MAX_SOUNDS_PER_SECOND: usize = 12;
+const CROSSFADE_DURATION: f64 = 2.0;
+
+pub trait AudioBackend {
+    fn play_sound(&mut self, sound: SoundEffect, volume: f32, pitch: f32);
+    fn start_music(&mut self, track: usize);
+    fn stop_music(&mut self, track: usize);
+    fn set_music_volume(&mut self, track: usize, volume: f32);
+    // Called once per frame to keep music streams fed
+    fn update(&mut self);
+}
+
+// Used when there is no audio device, e.g. headless builds and tests
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn play_sound(&mut self, _sound: SoundEffect, _volume: f32, _pitch: f32) {}
+    fn start_music(&mut self, _track: usize) {}
+    fn stop_music(&mut self, _track: usize) {}
+    fn set_music_volume(&mut self, _track: usize, _volume: f32) {}
+    fn update(&mut self) {}
+}
+
+// Fields drop in order, the sounds have to be unloaded before the device closes
+pub struct RaylibAudioBackend {
+    sounds: HashMap<SoundEffect, Sound>,
+    music: Vec<Option<Music>>,
+    audio: RaylibAudio,
+}
+
+impl RaylibAudioBackend {
+    // Missing files only silence that sound, no device means no backend
+    pub fn new(thread: &RaylibThread, assets: &LoadedAssets) -> Option<RaylibAudioBackend> {
+        let audio = RaylibAudio::init_audio_device();
+        if !audio.is_audio_device_ready() {
+            return None;
+        }
+        let mut sounds = HashMap::new();
+        for (effect, name) in SOUND_NAMES {
+            let source = match assets.sounds.get(name) {
+                Some(source) => source,
+                None => {
+                    eprintln!("Warning: no sound '{}' in the asset manifest", name);
+                    continue;
+                }
+            };
+            match load_sound(source) {
+                Ok(sound) => { sounds.insert(effect, sound); },
+                Err(e) => eprintln!("Warning: failed to load sound {}: {}", source, e),
+            }
+        }
+        let music = MUSIC_NAMES.iter()
+            .map(|name| {
+                let source = assets.music.get(*name)?;
+                match load_music(thread, source) {
+                    Ok(music) => Some(music),
+                    Err(e) => {
+                        eprintln!("Warning: failed to load music {}: {}", source, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Some(RaylibAudioBackend { sounds, music, audio })
+    }
+}
+
+impl AudioBackend for RaylibAudioBackend {
+    fn play_sound(&mut self, sound: SoundEffect, volume: f32, pitch: f32) {
+        if let Some(sound) = self.sounds.get(&sound) {
+            self.audio.set_sound_volume(sound, volume);
+            self.audio.set_sound_pitch(sound, pitch);
+            self.audio.play_sound_multi(sound);
+        }
+    }
+
+    fn start_music(&mut self, track: usize) {
+        if let Some(Some(music)) = self.music.get_mut(track) {
+            self.audio.play_music_stream(music);
+        }
+    }
+
+    fn stop_music(&mut self, track: usize) {
+        if let Some(Some(music)) = self.music.get_mut(track) {
+            self.audio.stop_music_stream(music);
+        }
+    }
+
+    fn set_music_volume(&mut self, track: usize, volume: f32) {
+        if let Some(Some(music)) = self.music.get_mut(track) {
+            self.audio.set_music_volume(music, volume);
+        }
+    }
+
+    fn update(&mut self) {
+        for music in self.music.iter_mut().flatten() {
+            self.audio.update_music_stream(music);
+        }
+    }
+}
+
+pub struct AudioSystem {
+    backend: Box<dyn AudioBackend>,
+    last_played: HashMap<SoundEffect, f64>,
+    // Start times of the sounds played during the last second
+    recent_plays: VecDeque<f64>,
+    current_track: Option<usize>,
+    // Track being faded out and when the crossfade started
+    previous_track: Option<usize>,
+    fade_start: f64,
+}
+
+impl AudioSystem {
+    pub fn new(backend: Box<dyn AudioBackend>) -> AudioSystem {
+        AudioSystem {
+            backend,
+            last_played: HashMap::new(),
+            recent_plays: VecDeque::new(),
+            current_track: None,
+            previous_track: None,
+            fade_start: 0f64,
+        }
+    }
+
+    // Plays sounds for this tick's events and keeps the music going. `now` is
+    // in seconds and only needs to increase.
+    pub fn update(&mut self, state: &GameState, settings: &Settings, now: f64) {
+        for event in &state.events {
+            match event {
+                GameEvent::UpgradePurchased { .. } | GameEvent::BoostStarted { .. } => {
+                    self.play(SoundEffect::Purchase, 1f32, settings, now);
+                },
+                GameEvent::ShapesDropped { sides, .. } => {
+                    self.play(SoundEffect::PipeDrop, pitch_for_sides(*sides), settings, now);
+                },
+                GameEvent::ShapeCut { sides, .. } => {
+                    self.play(SoundEffect::LaserCut, pitch_for_sides(*sides), settings, now);
+                },
+                // Only sales worth at least 2% of the score are worth hearing
+                GameEvent::ShapeSold { sides, price, .. } if price.mul_u64(50) >= state.score => {
+                    self.play(SoundEffect::BigSale, pitch_for_sides(*sides), settings, now);
+                },
+                _ => {},
+            }
+        }
+        let track = if state.pipes.len() >= 3 { 1 } else { 0 };
+        if self.current_track != Some(track) {
+            if let Some(previous) = self.previous_track {
+                self.backend.stop_music(previous);
+            }
+            self.previous_track = self.current_track;
+            self.current_track = Some(track);
+            self.fade_start = now;
+            self.backend.start_music(track);
+        }
+        self.crossfade(settings, now);
+        self.backend.update();
+    }
+
+    fn play(&mut self, effect: SoundEffect, pitch: f32, settings: &Settings, now: f64) {
+        while self.recent_plays.front().is_some_and(|t| now - t > 1f64) {
+            self.recent_plays.pop_front();
+        }
+        if self.recent_plays.len() >= MAX_SOUNDS_PER_SECOND {
+            return;
+        }
+        if let Some(last) = self.last_played.get(&effect) {
+            if now - last < MIN_REPEAT_INTERVAL {
+                return;
+            }
+        }
+        self.last_played.insert(effect, now);
+        self.recent_plays.push_back(now);
+        let volume = settings.master_volume * settings.sound_volume;
+        self.backend.play_sound(effect, volume, pitch);
+    }
+
+    fn crossfade(&mut self, settings: &Settings, now: f64) {
+        let volume = settings.master_volume * settings.music_volume;
+        let progress = ((now - self.fade_start) / CROSSFADE_DURATION).clamp(0f64, 1f64) as f32;
+        if let Some(track) = self.current_track {
+            self.backend.set_music_volume(track, volume * progress);
+        }
+        if let Some(previous) = self.previous_track {
+            self.backend.set_music_volume(previous, volume * (1f32 - progress));
+            if progress >= 1f32 {
+                self.backend.stop_music(previous);
+                self.previous_track = None;
+            }
+        }
+    }
+}
+
+// More sides, deeper sound
+fn pitch_for_sides(sides: i32) -> f32 {
+    (1.3 - 0.1 * (sides - 3) as f32).clamp(0.6, 1.6)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{game_logic::state::seeded_state, settings::default_settings};
+
+    // Remembers what it was asked to play instead of playing it
+    struct RecordingAudio(Rc<RefCell<Vec<SoundEffect>>>);
+
+    impl AudioBackend for RecordingAudio {
+        fn play_sound(&mut self, sound: SoundEffect, _volume: f32, _pitch: f32) {
+            self.0.borrow_mut().push(sound);
+        }
+        fn start_music(&mut self, _track: usize) {}
+        fn stop_music(&mut self, _track: usize) {}
+        fn set_music_volume(&mut self, _track: usize, _volume: f32) {}
+        fn update(&mut self) {}
+    }
+
+    fn recording_system() -> (AudioSystem, Rc<RefCell<Vec<SoundEffect>>>) {
+        let played = Rc::new(RefCell::new(vec![]));
+        (AudioSystem::new(Box::new(RecordingAudio(played.clone()))), played)
+    }
+
+    #[test]
+    fn an_effect_waits_before_repeating() {
+        let (mut audio, played) = recording_system();
+        let settings = default_settings();
+        for now in [0f64, 0.05, 0.079, 0.08, 0.1, 0.2] {
+            audio.play(SoundEffect::PipeDrop, 1f32, &settings, now);
+        }
+        // 0, 0.08 and 0.2, the rest came too soon after the last one played
+        assert_eq!(played.borrow().len(), 3);
+    }
+
+    #[test]
+    fn different_effects_do_not_wait_for_each_other() {
+        let (mut audio, played) = recording_system();
+        let settings = default_settings();
+        audio.play(SoundEffect::PipeDrop, 1f32, &settings, 1f64);
+        audio.play(SoundEffect::LaserCut, 1f32, &settings, 1f64);
+        audio.play(SoundEffect::BigSale, 1f32, &settings, 1f64);
+        assert_eq!(*played.borrow(), vec![SoundEffect::PipeDrop, SoundEffect::LaserCut, SoundEffect::BigSale]);
+    }
+
+    #[test]
+    fn only_a_handful_of_sounds_start_per_second() {
+        let (mut audio, played) = recording_system();
+        let settings = default_settings();
+        // Each effect every 0.1 s, so only the overall limit applies
+        let effects = [SoundEffect::Purchase, SoundEffect::PipeDrop, SoundEffect::LaserCut, SoundEffect::BigSale];
+        for i in 0..40 {
+            audio.play(effects[i % 4], 1f32, &settings, i as f64 * 0.025);
+        }
+        assert_eq!(played.borrow().len(), MAX_SOUNDS_PER_SECOND);
+        // The first plays are over a second old now
+        audio.play(SoundEffect::PipeDrop, 1f32, &settings, 1.5);
+        assert_eq!(played.borrow().len(), MAX_SOUNDS_PER_SECOND + 1);
+    }
+
+    #[test]
+    fn a_tick_full_of_drops_plays_one_sound() {
+        let (mut audio, played) = recording_system();
+        let mut state = seeded_state(1);
+        state.events = vec![GameEvent::ShapesDropped { sides: 3 }; 100];
+        audio.update(&state, &default_settings(), 0f64);
+        assert_eq!(*played.borrow(), vec![SoundEffect::PipeDrop]);
+    }
+
+    #[test]
+    fn null_audio_takes_events_and_music_changes() {
+        let mut audio = AudioSystem::new(Box::new(NullAudio));
+        let mut state = seeded_state(1);
+        state.events = vec![GameEvent::ShapesDropped { sides: 3 }, GameEvent::ShapeCut { sides: 4, x: 0f64, y: 0f64 }];
+        audio.update(&state, &default_settings(), 0f64);
+        assert_eq!(audio.current_track, Some(0));
+        state.pipes.push(state.pipes[0].clone());
+        state.pipes.push(state.pipes[0].clone());
+        audio.update(&state, &default_settings(), 1f64);
+        assert_eq!((audio.current_track, audio.previous_track), (Some(1), Some(0)));
+        audio.update(&state, &default_settings(), 1f64 + CROSSFADE_DURATION);
+        assert_eq!(audio.previous_track, None);
+    }
+}
@@ -0,0 +1,344 @@
+// Plays through the upgrade catalog with a bot and prints a timeline of the
+// purchases, to spot slow stretches in the cost curves without playing.
+//
+//   cargo run --bin balance_sim -- --strategy lookahead:3 --format json
+//
+// Income is worked out from the drop rates and shape prices instead of
+// simulating every shape, so a full run takes a moment. Shapes are sold as
+// soon as they drop, the few seconds they take to fall are ignored, so
+// assets/physics.txt has no effect here.
+
+use std::{env, fs, process};
+
+use num::{BigInt, ToPrimitive};
+
+use match_game::game_logic::{
+    achievements::achievements_tick,
+    events::GameEvent,
+    laser::active_lasers,
+    pipe::{drop_delay, pipe_tick, unlocked_colors},
+    score::Score,
+    multipliers::active_multipliers,
+    prices::price_table,
+    shape::regular_shape,
+    state::{GameState, initial_state},
+    upgrades::{Upgrade, load_upgrade_overrides, purchase_upgrade, unlock_upgrades::unlock_upgrades_tick},
+};
+
+// How many of the cheapest upgrades the lookahead tries at each step
+const LOOKAHEAD_BRANCHING: usize = 4;
+const DEFAULT_LOOKAHEAD_DEPTH: usize = 3;
+// Waits reported as dead zones in the summary
+const DEAD_ZONES_SHOWN: usize = 5;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Strategy {
+    // Buy whatever is cheapest
+    Cheapest,
+    // Buy what pays for itself soonest, counting the wait to afford it
+    BestRoi,
+    // Try every order of the next few purchases and take the first step of
+    // the one with the most score at a common point in time
+    Lookahead(usize),
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Options {
+    strategy: Strategy,
+    format: Format,
+    max_hours: f64,
+    overrides: Option<String>,
+}
+
+struct Purchase {
+    // Seconds since the start
+    time: f64,
+    upgrade: Upgrade,
+    price: BigInt,
+    // Seconds spent saving up for this upgrade
+    wait: f64,
+    // Just before paying, the bot spends everything so this is the peak
+    score: Score,
+    // After the purchase
+    income: f64,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("Usage: balance_sim [--strategy cheapest|roi|lookahead[:depth]] [--format csv|json] [--max-hours n] [--upgrades file]");
+        process::exit(2);
+    });
+    if let Some(path) = &options.overrides {
+        match fs::read_to_string(path) {
+            Ok(contents) => load_upgrade_overrides(&contents),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+    let (timeline, completed) = simulate(options.strategy, options.max_hours * 3600f64);
+    match options.format {
+        Format::Csv => print_csv(&timeline),
+        Format::Json => print_json(&timeline, options.strategy, completed),
+    }
+    print_summary(&timeline, options.strategy, completed);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        strategy: Strategy::BestRoi,
+        format: Format::Csv,
+        max_hours: 10_000f64,
+        overrides: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--strategy" => options.strategy = parse_strategy(&value()?)?,
+            "--format" => options.format = match value()?.as_str() {
+                "csv" => Format::Csv,
+                "json" => Format::Json,
+                other => return Err(format!("unknown format '{}'", other)),
+            },
+            "--max-hours" => {
+                let hours = value()?;
+                options.max_hours = hours.parse().map_err(|_| format!("bad hours '{}'", hours))?;
+            },
+            "--upgrades" => options.overrides = Some(value()?),
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_strategy(name: &str) -> Result<Strategy, String> {
+    match name.split_once(':') {
+        None if name == "cheapest" => Ok(Strategy::Cheapest),
+        None if name == "roi" => Ok(Strategy::BestRoi),
+        None if name == "lookahead" => Ok(Strategy::Lookahead(DEFAULT_LOOKAHEAD_DEPTH)),
+        Some(("lookahead", depth)) => match depth.parse() {
+            Ok(depth) if depth > 0 => Ok(Strategy::Lookahead(depth)),
+            _ => Err(format!("bad lookahead depth '{}'", depth)),
+        },
+        _ => Err(format!("unknown strategy '{}'", name)),
+    }
+}
+
+// Returns the purchases and whether the whole catalog was bought in time
+fn simulate(strategy: Strategy, max_time: f64) -> (Vec<Purchase>, bool) {
+    let mut state = initial_state();
+    let mut time = 0f64;
+    let mut timeline = vec![];
+    settle(&mut state);
+    while !state.available_upgrades.is_empty() {
+        let index = choose(&state, strategy);
+        let upgrade = state.available_upgrades[index].clone();
+        let wait = save_up(&mut state, &upgrade.price);
+        if time + wait > max_time {
+            return (timeline, false);
+        }
+        time += wait;
+        let score = state.score.clone();
+        buy(&mut state, index);
+        timeline.push(Purchase {
+            time,
+            upgrade: upgrade.upgrade,
+            price: upgrade.price,
+            wait,
+            score,
+            income: income_per_second(&state),
+        });
+    }
+    (timeline, true)
+}
+
+// Lets the game apply the upgrades owned so far: pipes, speeds, achievements
+// and which upgrades are up for sale
+fn settle(state: &mut GameState) {
+    // Twice since new pipes only get their speed on the following tick
+    pipe_tick(state, 0);
+    pipe_tick(state, 0);
+    // Every kind of shape being made counts as sold for the sale achievements
+    let laser = laser_sides(state);
+    for i in 0..state.pipes.len() {
+        for color in unlocked_colors(state, i as i32) {
+            let sides = state.pipes[i].sides + laser;
+            state.events.push(GameEvent::ShapeSold { sides, color, price: Score::zero(), x: 0f64, y: 0f64 });
+        }
+    }
+    achievements_tick(state, 0);
+    state.events.clear();
+    unlock_upgrades_tick(state);
+}
+
+fn buy(state: &mut GameState, index: usize) {
+    purchase_upgrade(state, index);
+    settle(state);
+}
+
+// Assumes every laser reaches all the drop spots and adds a side, sliced
+// shapes are not modelled
+fn laser_sides(state: &GameState) -> i32 {
+    active_lasers(state).len() as i32
+}
+
+fn income_per_second(state: &GameState) -> f64 {
+    let multipliers = active_multipliers(state);
+    let laser = laser_sides(state);
+    let mut income = 0f64;
+    for (i, pipe) in state.pipes.iter().enumerate() {
+        let drops_per_second = 1000f64 / drop_delay(pipe) as f64;
+        for color in unlocked_colors(state, i as i32) {
+            let price = price_table()
+                .sale_price(&regular_shape(pipe.sides + laser, color, 0f64, 0f64), &multipliers)
+                .map_or(0f64, |price| price.to_f64());
+            income += drops_per_second * price;
+        }
+    }
+    income
+}
+
+// Advances time until the score reaches `price`, returns the seconds waited
+fn save_up(state: &mut GameState, price: &BigInt) -> f64 {
+    if state.score >= *price {
+        return 0f64;
+    }
+    let missing = (price - state.score.to_bigint()).to_f64().unwrap_or(f64::MAX);
+    let wait = missing / income_per_second(state);
+    // Rounding must never leave the bot just short
+    state.score = Score::from_bigint(price).unwrap_or_else(Score::zero);
+    achievements_tick(state, 0);
+    wait
+}
+
+fn choose(state: &GameState, strategy: Strategy) -> usize {
+    // `available_upgrades` is sorted by price
+    match strategy {
+        Strategy::Cheapest => 0,
+        Strategy::BestRoi => {
+            let income = income_per_second(state);
+            (0..state.available_upgrades.len())
+                .map(|index| (index, payback(state, index, income, 1)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map_or(0, |best| best.0)
+        },
+        Strategy::Lookahead(depth) => {
+            let mut leaves = vec![];
+            look_ahead(state, depth, None, 0f64, &mut leaves);
+            let horizon = leaves.iter().map(|l| l.1).fold(0f64, f64::max);
+            let value = |(_, time, score, income): &(usize, f64, f64, f64)| score + income * (horizon - time);
+            leaves.iter()
+                .max_by(|a, b| value(a).total_cmp(&value(b)))
+                .map_or(0, |leaf| leaf.0)
+        },
+    }
+}
+
+// Seconds until buying an upgrade has paid for itself, counting the wait to
+// afford it. Upgrades that earn nothing by themselves, like the first speed
+// level of a new pipe, are judged by the best upgrade they lead to.
+fn payback(state: &GameState, index: usize, income: f64, follow_ups: usize) -> f64 {
+    let mut next = state.clone();
+    let price = next.available_upgrades[index].price.clone();
+    let wait = save_up(&mut next, &price);
+    buy(&mut next, index);
+    let gain = income_per_second(&next) - income;
+    if gain > 0f64 {
+        return wait + price.to_f64().unwrap_or(f64::MAX) / gain;
+    }
+    if follow_ups == 0 {
+        return f64::INFINITY;
+    }
+    let best_follow_up = (0..next.available_upgrades.len())
+        .map(|i| payback(&next, i, income, follow_ups - 1))
+        .fold(f64::INFINITY, f64::min);
+    wait + best_follow_up
+}
+
+// Collects (first purchase, time taken, score, income) for every order of the
+// next `depth` purchases among the cheapest few
+fn look_ahead(
+    state: &GameState,
+    depth: usize,
+    first: Option<usize>,
+    time: f64,
+    leaves: &mut Vec<(usize, f64, f64, f64)>
+) {
+    if depth == 0 || state.available_upgrades.is_empty() {
+        if let Some(first) = first {
+            let score = state.score.to_f64();
+            leaves.push((first, time, score, income_per_second(state)));
+        }
+        return;
+    }
+    for index in 0..state.available_upgrades.len().min(LOOKAHEAD_BRANCHING) {
+        let mut next = state.clone();
+        let price = next.available_upgrades[index].price.clone();
+        let wait = save_up(&mut next, &price);
+        buy(&mut next, index);
+        look_ahead(&next, depth - 1, Some(first.unwrap_or(index)), time + wait, leaves);
+    }
+}
+
+fn print_csv(timeline: &[Purchase]) {
+    println!("time_s,upgrade,price,wait_s,score,income_per_s");
+    for p in timeline {
+        println!("{:.1},{},{},{:.1},{},{:.3e}", p.time, p.upgrade, p.price, p.wait, p.score, p.income);
+    }
+}
+
+// Big numbers are strings since JSON readers tend to parse them as doubles
+fn print_json(timeline: &[Purchase], strategy: Strategy, completed: bool) {
+    println!("{{");
+    println!("  \"strategy\": \"{:?}\",", strategy);
+    println!("  \"completed\": {},", completed);
+    println!("  \"total_time_s\": {:.1},", timeline.last().map_or(0f64, |p| p.time));
+    println!("  \"timeline\": [");
+    for (i, p) in timeline.iter().enumerate() {
+        let comma = if i + 1 < timeline.len() { "," } else { "" };
+        println!(
+            "    {{\"time_s\": {:.1}, \"upgrade\": \"{}\", \"price\": \"{}\", \"wait_s\": {:.1}, \"score\": \"{}\", \"income_per_s\": {:.3e}}}{}",
+            p.time, p.upgrade, p.price, p.wait, p.score, p.income, comma
+        );
+    }
+    println!("  ]");
+    println!("}}");
+}
+
+fn print_summary(timeline: &[Purchase], strategy: Strategy, completed: bool) {
+    let total = timeline.last().map_or(0f64, |p| p.time);
+    eprintln!("Strategy: {:?}", strategy);
+    eprintln!("Bought {} upgrades in {}", timeline.len(), format_duration(total));
+    if !completed {
+        eprintln!("Stopped at the time limit before buying everything");
+    }
+    let mut waits: Vec<&Purchase> = timeline.iter().collect();
+    waits.sort_by(|a, b| b.wait.total_cmp(&a.wait));
+    eprintln!("Longest waits:");
+    for p in waits.iter().take(DEAD_ZONES_SHOWN) {
+        eprintln!(
+            "  {} saving for {} (at {}, {:.1}% of the run)",
+            format_duration(p.wait),
+            p.upgrade,
+            format_duration(p.time),
+            p.wait / total.max(1f64) * 100f64
+        );
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0f64) as u64;
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        return format!("{}d {}h {}m", days, hours, minutes);
+    }
+    format!("{}h {}m {}s", hours, minutes, seconds % 60)
+}
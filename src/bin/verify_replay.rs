@@ -0,0 +1,36 @@
+// Plays a replay without opening a window and checks that it ends in the
+// recorded state, e.g. for a replay attached to a bug report:
+//
+//   cargo run --bin verify_replay -- session.replay
+
+use std::{env, process};
+
+use match_game::replay::{read_replay, run_replay, state_hash};
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: verify_replay <file>");
+            process::exit(2);
+        }
+    };
+    let replay = read_replay(&path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let state = run_replay(&replay).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let hash = state_hash(&state);
+    println!("score {}, {} upgrades, {} shapes", state.score, state.upgrades.len(), state.shapes.len());
+    match replay.hash {
+        Some(expected) if expected == hash => println!("State hash {:016x} matches", hash),
+        Some(expected) => {
+            println!("State hash {:016x} does not match the recorded {:016x}", hash, expected);
+            process::exit(1);
+        },
+        None => println!("State hash {:016x}, the replay has no hash to compare with", hash),
+    }
+}
@@ -0,0 +1,190 @@
+use raylib::{RaylibHandle, consts::KeyboardKey, prelude::{Color, RaylibDraw}};
+
+use crate::{
+    game_logic::{
+        colors::SHAPE_COLORS,
+        commands::{SHAPE_NAMES, run_command},
+        state::GameState,
+        upgrades::unlock_upgrades::all_upgrades,
+    },
+    layout::Layout,
+};
+
+const TOGGLE_KEY: KeyboardKey = KeyboardKey::KEY_GRAVE;
+const MAX_OUTPUT_LINES: usize = 200;
+// Most completions listed after an ambiguous Tab
+const MAX_LISTED_COMPLETIONS: usize = 12;
+
+const COMMANDS: [&str; 7] = ["help", "score", "upgrade", "timescale", "spawn", "cutmode", "state"];
+
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub output: Vec<String>,
+    history: Vec<String>,
+    // Position while browsing the history, None when editing a new line
+    history_index: Option<usize>,
+}
+
+pub fn closed_console() -> Console {
+    Console { open: false, input: String::new(), output: vec![], history: vec![], history_index: None }
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        if self.output.len() > MAX_OUTPUT_LINES {
+            self.output.remove(0);
+        }
+    }
+}
+
+// Takes the keyboard while the console is open, nothing typed reaches the game.
+// Returns the command that ran this frame, for the replay recorder.
+pub fn console_tick(rl: &mut RaylibHandle, console: &mut Console, state: &mut GameState) -> Option<String> {
+    if rl.is_key_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+        drain_chars();
+        state.actions.clear();
+        return None;
+    }
+    if !console.open {
+        return None;
+    }
+    state.actions.clear();
+    for c in drain_chars() {
+        console.input.push(c);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+        console.input.pop();
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_TAB) {
+        complete(console);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_UP) && !console.history.is_empty() {
+        let index = match console.history_index {
+            Some(i) => i.saturating_sub(1),
+            None => console.history.len() - 1,
+        };
+        console.history_index = Some(index);
+        console.input = console.history[index].clone();
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+        if let Some(i) = console.history_index {
+            if i + 1 < console.history.len() {
+                console.history_index = Some(i + 1);
+                console.input = console.history[i + 1].clone();
+            } else {
+                console.history_index = None;
+                console.input.clear();
+            }
+        }
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+        let line = std::mem::take(&mut console.input);
+        console.history_index = None;
+        if line.trim().is_empty() {
+            return None;
+        }
+        console.print(format!("> {}", line));
+        if console.history.last() != Some(&line) {
+            console.history.push(line.clone());
+        }
+        match run_command(state, &line) {
+            Ok(lines) => {
+                if line.trim() == "state dump" {
+                    lines.iter().for_each(|l| println!("{}", l));
+                }
+                lines.into_iter().for_each(|l| console.print(l));
+                return Some(line);
+            },
+            Err(e) => console.print(format!("error: {}", e)),
+        }
+    }
+    None
+}
+
+// Printable ASCII typed since the last frame, the default font has nothing else
+fn drain_chars() -> Vec<char> {
+    let mut chars = vec![];
+    loop {
+        let c = unsafe { raylib::ffi::GetCharPressed() };
+        if c <= 0 {
+            return chars;
+        }
+        match char::from_u32(c as u32) {
+            Some(c) if c.is_ascii() && !c.is_ascii_control() && c != '`' => chars.push(c),
+            _ => {},
+        }
+    }
+}
+
+// Completes the word being typed from whatever fits at that position
+fn complete(console: &mut Console) {
+    let start = console.input.rfind(' ').map_or(0, |i| i + 1);
+    let words: Vec<&str> = console.input[..start].split_whitespace().collect();
+    let candidates: Vec<String> = match words.as_slice() {
+        [] => COMMANDS.iter().map(|c| c.to_string()).collect(),
+        ["score"] => vec!["set".to_string(), "add".to_string()],
+        ["upgrade"] => vec!["grant".to_string(), "revoke".to_string()],
+        ["state"] => vec!["dump".to_string()],
+        ["cutmode"] => vec!["add_side".to_string(), "slice".to_string()],
+        ["upgrade", _] => upgrade_names(),
+        ["spawn"] => SHAPE_NAMES.iter().map(|(name, _)| name.to_string()).collect(),
+        ["spawn", _] => SHAPE_COLORS.iter()
+            .map(|(_, name)| name.to_lowercase())
+            .chain(["gray".to_string()])
+            .collect(),
+        _ => vec![],
+    };
+    let prefix = &console.input[start..];
+    let matches: Vec<&String> = candidates.iter().filter(|c| c.starts_with(prefix)).collect();
+    match matches.as_slice() {
+        [] => {},
+        [only] => {
+            let only = only.to_string();
+            console.input.truncate(start);
+            console.input += &only;
+            console.input.push(' ');
+        },
+        _ => {
+            let common = matches.iter().skip(1).fold(matches[0].as_str(), |common, m| {
+                let len = common.chars().zip(m.chars()).take_while(|(a, b)| a == b).count();
+                &common[..len]
+            }).to_string();
+            console.input.truncate(start);
+            console.input += &common;
+            let listed: Vec<&str> = matches.iter().take(MAX_LISTED_COMPLETIONS).map(|m| m.as_str()).collect();
+            let more = if matches.len() > listed.len() { " ..." } else { "" };
+            console.print(format!("{}{}", listed.join(" "), more));
+        },
+    }
+}
+
+// Every upgrade in the catalog, named the way `Upgrade::from_str` reads them
+fn upgrade_names() -> Vec<String> {
+    all_upgrades().iter().map(|u| u.upgrade.to_string()).collect()
+}
+
+pub fn draw_console(d: &mut impl RaylibDraw, console: &Console, layout: &Layout) {
+    if !console.open {
+        return;
+    }
+    let width = layout.panel.0 + layout.panel.2;
+    let height = layout.height * 2 / 5;
+    let font_size = layout.px(16);
+    let line_height = font_size + layout.px(4);
+    let margin = layout.px(8);
+    d.draw_rectangle(0, 0, width, height, Color::new(0, 0, 0, 210));
+    let input_y = height - margin - line_height;
+    d.draw_text(&format!("> {}_", console.input), margin, input_y, font_size, Color::LIME);
+    let mut y = input_y - line_height;
+    for line in console.output.iter().rev() {
+        if y < margin {
+            break;
+        }
+        let color = if line.starts_with("error") { Color::RED } else { Color::WHITE };
+        d.draw_text(line, margin, y, font_size, color);
+        y -= line_height;
+    }
+}
@@ -1,79 +1,580 @@
 use num::BigInt;
-use raylib::{RaylibHandle, RaylibThread, prelude::{Color, RaylibDrawHandle, RaylibDraw, Vector2}};
+use raylib::{prelude::{Color, RaylibDraw, RaylibDrawHandle, RaylibMode2DExt, RaylibScissorModeExt, Rectangle, Vector2}};
 
-use crate::{game_logic::{state::GameState, upgrades::Upgrade}, assets::LoadedAssets};
+use crate::{
+    game_logic::{
+        action::Action,
+        boosts::{BoostKind, boost_info},
+        emission::{pipe_emission, pipe_stats},
+        state::GameState, 
+        tick,
+        colors::color_name,
+        laser::active_lasers,
+        multipliers::{MultiplierCategory, active_multipliers},
+        pipe::pipe_position,
+        walls::{SegmentKind, segments},
+        shape::shape_name,
+        achievements::{ALL_ACHIEVEMENTS, achievement_info}, 
+        world::{WORLD_WIDTH, WORLD_HEIGHT}
+    }, 
+    assets::LoadedAssets, 
+    i18n::{Text, language_name, text},
+    input::key_name,
+    layout::Layout,
+    particles::{ParticleSpace, Particles},
+    pipe_skins::{BADGE_CENTER, BADGE_RADIUS, COLLAR, DROP_PULSE, pipe_skin},
+    settings::{Notation, Settings, SettingsMenu, SettingsRow, settings_row_rect, settings_rows},
+};
 
-pub const WIDTH: i32 = 1280;
-pub const HEIGHT: i32 = 720;
+pub fn draw(
+    d: &mut RaylibDrawHandle, 
+    game_state: &GameState, 
+    loaded_assets: &LoadedAssets, 
+    layout: &Layout, 
+    settings: &Settings, 
+    menu: &SettingsMenu,
+    particles: &Particles
+) {
+    d.clear_background(Color::new(150, 150, 170, 255));
+    {
+        let (x, y, width, height) = layout.playfield;
+        let mut d = d.begin_scissor_mode(x, y, width, height);
+        let mut d = d.begin_mode2D(layout.camera);
+        d.draw_rectangle(0, 0, WORLD_WIDTH, WORLD_HEIGHT, Color::new(220, 220, 240, 255));
+        draw_segments(&mut d, game_state);
+        draw_shapes(&mut d, game_state);
+        draw_pipes(loaded_assets, &mut d, game_state);
+        draw_shape_upgrade_lasers(&mut d, game_state);
+        particles.draw(&mut d, ParticleSpace::World, layout);
+    }
+    draw_ui(d, game_state, loaded_assets, layout, settings);
+    draw_multipliers(d, game_state, layout, settings);
+    draw_inspection(d, game_state, layout, settings);
+    draw_pipe_panel(d, game_state, layout, settings);
+    particles.draw(d, ParticleSpace::Screen, layout);
+    if game_state.show_achievements {
+        draw_achievements(d, game_state, layout, settings);
+    }
+    if game_state.paused {
+        draw_paused(d, layout, settings);
+    }
+    if menu.open {
+        draw_settings(d, layout, settings, menu);
+    }
+    draw_achievement_notifications(d, game_state, layout, settings);
+}
 
-pub fn draw(rl: &mut RaylibHandle, thread: &RaylibThread, game_state: &GameState, loaded_assets: &LoadedAssets) {
-    let mut d = rl.begin_drawing(&thread);         
-    d.clear_background(Color::new(220, 220, 240, 255));
-    draw_shapes(&mut d, game_state);
-    draw_pipes(loaded_assets, &mut d, game_state);
-    draw_shape_upgrade_lasers(&mut d, game_state);
-    draw_ui(loaded_assets, &mut d, game_state);
+// Index into `available_upgrades` of the buy button under the given point
+pub fn upgrade_at(game_state: &GameState, layout: &Layout, x: i32, y: i32) -> Option<usize> {
+    let visible = game_state.available_upgrades.len()
+        .saturating_sub(game_state.shop_scroll)
+        .min(layout.shop_rows());
+    (0..visible)
+        .find(|row| {
+            let (button_x, button_y, width, height) = layout.upgrade_button_rect(*row);
+            (button_x..(button_x+width)).contains(&x) && (button_y..(button_y+height)).contains(&y)
+        })
+        .map(|row| game_state.shop_scroll + row)
+}
+
+// Rectangle of the open pipe panel, if any. Clicks inside it don't reach the
+// playfield below.
+pub fn pipe_panel_rect(game_state: &GameState, layout: &Layout) -> Option<(i32, i32, i32, i32)> {
+    let pipe = game_state.pipe_panel?;
+    Some(layout.pipe_panel_rect(pipe_emission(game_state, pipe).count()))
+}
+
+// The action of the pipe panel button under the given point
+pub fn pipe_panel_action(game_state: &GameState, layout: &Layout, x: i32, y: i32) -> Option<Action> {
+    let pipe = game_state.pipe_panel?;
+    let inside = |(rect_x, rect_y, width, height): (i32, i32, i32, i32)| {
+        (rect_x..(rect_x+width)).contains(&x) && (rect_y..(rect_y+height)).contains(&y)
+    };
+    if inside(layout.pipe_panel_close_rect()) {
+        return Some(Action::ClosePipePanel);
+    }
+    for row in 0..pipe_emission(game_state, pipe).count() {
+        if inside(layout.pipe_panel_toggle_rect(row)) {
+            return Some(Action::TogglePipeColor(row));
+        }
+        for delta in [-1, 1] {
+            if inside(layout.pipe_panel_weight_rect(row, delta)) {
+                return Some(Action::AdjustPipeMix(row, delta));
+            }
+        }
+    }
+    None
 }
 
-fn draw_shape_upgrade_lasers(d: &mut RaylibDrawHandle, game_state: &GameState) {
-    if game_state.upgrades.contains(&Upgrade::ShapeLaser(1)) {
-        d.draw_rectangle(0, HEIGHT/2 + 150, WIDTH, 7, Color::RED);
-        d.draw_rectangle(0, HEIGHT/2+2 + 150, WIDTH, 3, Color::new(255, 100, 100, 255));
+fn draw_shape_upgrade_lasers(d: &mut impl RaylibDraw, game_state: &GameState) {
+    for (_, laser) in active_lasers(game_state) {
+        let ((x1, y1), (x2, y2)) = laser.endpoints();
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt() as f32;
+        let center = Vector2::new(((x1 + x2) / 2f64) as f32, ((y1 + y2) / 2f64) as f32);
+        let thickness = laser.thickness as f32;
+        for (height, color) in [(thickness, Color::RED), (thickness * 3f32 / 7f32, Color::new(255, 100, 100, 255))] {
+            d.draw_rectangle_pro(
+                Rectangle::new(center.x, center.y, length, height), 
+                Vector2::new(length / 2f32, height / 2f32), 
+                laser.angle as f32, 
+                color
+            );
+        }
     }
 }
 
-fn draw_shapes(d: &mut RaylibDrawHandle, game_state: &GameState) {
+// Shapes are drawn between their last two simulated positions, so they move
+// smoothly when the frame rate does not match the simulation step
+fn draw_shapes(d: &mut impl RaylibDraw, game_state: &GameState) {
+    let alpha = tick::interpolation(game_state);
     for shape in &game_state.shapes {
-        d.draw_poly(
-            Vector2::new(shape.x as f32, shape.y as f32), 
-            shape.sides, 
-            25f32, 
-            shape.rot, 
-            shape.color
-        );
+        let x = shape.prev_x + (shape.x - shape.prev_x) * alpha;
+        let y = shape.prev_y + (shape.y - shape.prev_y) * alpha;
+        let corners: Vec<Vector2> = shape.corners_at(x, y)
+            .iter()
+            .map(|(cx, cy)| Vector2::new(*cx as f32, *cy as f32))
+            .collect();
+        d.draw_triangle_fan(&corners, shape.color);
+        if shape.held {
+            for i in 0..corners.len() {
+                d.draw_line_ex(corners[i], corners[(i + 1) % corners.len()], 2f32, Color::WHITE);
+            }
+        }
+        if shape.golden {
+            for i in 0..corners.len() {
+                d.draw_line_ex(corners[i], corners[(i + 1) % corners.len()], 3f32, Color::ORANGE);
+            }
+        }
     }
 }
 
-fn draw_pipes(loaded_assets: &LoadedAssets, d: &mut RaylibDrawHandle, game_state: &GameState) {
-    for (i, _pipe) in game_state.pipes.iter().enumerate().rev() {
-        let i = i as i32;
-        let darkness: u8 = (255 - i*20).try_into().unwrap();
-        d.draw_texture(
-            &loaded_assets.pipe_texture,
-            30 + 120*i,
-            30 + 10*i,
-            &Color::new(darkness, darkness, darkness, 255)
+fn draw_segments(d: &mut impl RaylibDraw, game_state: &GameState) {
+    for segment in segments(game_state) {
+        let (color, thickness) = match segment.kind {
+            SegmentKind::Wall => (Color::new(90, 90, 110, 255), 4f32),
+            SegmentKind::Bumper => (Color::ORANGE, 8f32),
+            SegmentKind::Ramp => (Color::DARKGRAY, 6f32),
+        };
+        let start = Vector2::new(segment.start.0 as f32, segment.start.1 as f32);
+        let end = Vector2::new(segment.end.0 as f32, segment.end.1 as f32);
+        d.draw_line_ex(start, end, thickness, color);
+        d.draw_circle_v(start, thickness / 2f32, color);
+        d.draw_circle_v(end, thickness / 2f32, color);
+    }
+    // Where the bumper or ramp being placed starts
+    if let Some((x, y)) = game_state.segment_start {
+        d.draw_circle_lines(x as i32, y as i32, 6f32, Color::DARKGRAY);
+    }
+}
+
+fn draw_pipes(loaded_assets: &LoadedAssets, d: &mut impl RaylibDraw, game_state: &GameState) {
+    for (i, pipe) in game_state.pipes.iter().enumerate().rev() {
+        let skin = pipe_skin(pipe.sides);
+        let (x, y) = pipe_position(i);
+        let (x, y) = (x as f32, y as f32);
+        d.draw_texture_rec(
+            &loaded_assets.atlas,
+            loaded_assets.texture_region(skin.texture),
+            Vector2::new(x, y),
+            skin.tint
         );
+        // Collar striped with the colors the pipe emits, wider for heavier mixes
+        let (collar_x, collar_y, collar_width, collar_height) = COLLAR;
+        let total: u32 = pipe_emission(game_state, i).filter(|e| e.enabled).map(|e| e.weight).sum();
+        let mut stripe_x = x + collar_x;
+        for entry in pipe_emission(game_state, i).filter(|e| e.enabled) {
+            let width = collar_width * entry.weight as f32 / total as f32;
+            d.draw_rectangle_v(Vector2::new(stripe_x, y + collar_y), Vector2::new(width, collar_height), entry.color.fade(0.8));
+            stripe_x += width;
+        }
+        // `last_drop` starts over at every drop, the badge swells and fades back
+        let pulse = 1f32 - pipe.last_drop.min(DROP_PULSE) as f32 / DROP_PULSE as f32;
+        let badge = Vector2::new(x + BADGE_CENTER.0, y + BADGE_CENTER.1);
+        if pulse > 0f32 {
+            d.draw_circle_v(badge, BADGE_RADIUS * 2f32, Color::WHITE.fade(0.4 * pulse));
+        }
+        d.draw_poly(badge, pipe.sides, BADGE_RADIUS * (1f32 + 0.3 * pulse), 180f32, skin.trim);
     }
 }
 
-fn draw_ui(loaded_assets: &LoadedAssets, d: &mut RaylibDrawHandle, game_state: &GameState) {
-    let mut y = 32;
-    d.draw_rectangle(WIDTH/2, 0, WIDTH/2, HEIGHT, Color::new(70, 70, 100, 255));
-    d.draw_text(&format_score(game_state), WIDTH/2 + 32, y, 24, Color::WHITE);
-    y += 24 + 12;
-    d.draw_text("Upgrades", WIDTH/2 + 32, y, 32, Color::WHITE);
-    y += 32 + 12;
-    for upgrade in &game_state.available_upgrades {
-        d.draw_text(&upgrade.description, WIDTH/2 + 32, y + 10, 16, Color::WHITE);
-        let box_color = if upgrade.price > game_state.score {
+fn draw_ui(d: &mut impl RaylibDraw, game_state: &GameState, loaded_assets: &LoadedAssets, layout: &Layout, settings: &Settings) {
+    let (panel_x, panel_y, panel_width, panel_height) = layout.panel;
+    let text_x = panel_x + layout.px(32);
+    let mut y = panel_y + layout.px(32);
+    d.draw_rectangle(panel_x, panel_y, panel_width, panel_height, Color::new(70, 70, 100, 255));
+    let score = format_score(game_state, settings);
+    match loaded_assets.fonts.get("ui") {
+        Some(font) => {
+            let position = Vector2::new(text_x as f32, y as f32);
+            d.draw_text_ex(font, &score, position, layout.px(24) as f32, 1f32, Color::WHITE);
+        },
+        None => d.draw_text(&score, text_x, y, layout.px(24), Color::WHITE),
+    }
+    draw_boosts(d, game_state, layout, settings, y);
+    y += layout.px(24 + 12);
+    d.draw_text(text(settings.language, Text::Upgrades), text_x, y, layout.px(32), Color::WHITE);
+    let visible_upgrades = game_state.available_upgrades.iter()
+        .enumerate()
+        .skip(game_state.shop_scroll)
+        .take(layout.shop_rows());
+    for (row, (i, upgrade)) in visible_upgrades.enumerate() {
+        let (button_x, y, button_width, button_height) = layout.upgrade_button_rect(row);
+        if i == game_state.shop_selection {
+            d.draw_rectangle(
+                panel_x + layout.px(16), 
+                y - layout.px(4), 
+                panel_width - layout.px(32), 
+                button_height + layout.px(8), 
+                Color::new(90, 90, 130, 255)
+            );
+        }
+        let text_y = y + layout.px(10);
+        d.draw_text(&upgrade.description, text_x, text_y, layout.px(16), Color::WHITE);
+        let box_color = if game_state.score < upgrade.price {
             Color::new(40, 120, 40, 255)
         } else {
             Color::new(80, 180, 80, 255)
         };
-        let box_text_color = if upgrade.price > game_state.score {
+        let box_text_color = if game_state.score < upgrade.price {
             Color::GRAY
         } else {
             Color::WHITE
         };
-        d.draw_rectangle(WIDTH - 50 - 32, y, 50, 36, box_color);
-        d.draw_text("Buy", WIDTH - 50 - 32 + 10, y + 10, 16, box_text_color);
-        d.draw_text(&format_num(&upgrade.price, true), WIDTH - 50 - 32 - 80 - 12, y + 10, 16, Color::WHITE);
-        y += 36 + 12;
+        d.draw_rectangle(button_x, y, button_width, button_height, box_color);
+        d.draw_text(text(settings.language, Text::Buy), button_x + layout.px(10), text_y, layout.px(16), box_text_color);
+        d.draw_text(
+            &format_num(&upgrade.price, settings.notation, true), 
+            button_x - layout.px(80 + 12), 
+            text_y, 
+            layout.px(16), 
+            Color::WHITE
+        );
+    }
+}
+
+// Countdown icons for the running boosts, right aligned on the score row
+fn draw_boosts(d: &mut impl RaylibDraw, game_state: &GameState, layout: &Layout, settings: &Settings, y: i32) {
+    let (panel_x, _, panel_width, _) = layout.panel;
+    let font_size = layout.px(16);
+    let radius = layout.px(10);
+    let mut right = panel_x + panel_width - layout.px(32);
+    for boost in game_state.boosts.iter().rev() {
+        let (factor, duration) = boost_info(boost.kind);
+        let name = match boost.kind {
+            BoostKind::DropSpeed => Text::DropBoost,
+            BoostKind::SaleValue => Text::SaleBoost,
+        };
+        let label = format!("{} x{} {}s", text(settings.language, name), factor, boost.remaining.div_ceil(1000));
+        let label_width = raylib::core::text::measure_text(&label, font_size);
+        let center = Vector2::new((right - label_width - layout.px(6) - radius) as f32, (y + layout.px(12)) as f32);
+        // The ring empties as the boost runs out
+        let left = 360f32 * boost.remaining as f32 / duration as f32;
+        d.draw_circle_v(center, radius as f32, Color::new(40, 40, 60, 255));
+        d.draw_circle_sector(center, radius as f32, 180f32, 180f32 - left, 24, Color::GOLD);
+        d.draw_text(&label, right - label_width, y + layout.px(12) - font_size/2, font_size, Color::GOLD);
+        right -= label_width + radius * 2 + layout.px(6 + 16);
+    }
+}
+
+fn draw_paused(d: &mut impl RaylibDraw, layout: &Layout, settings: &Settings) {
+    let (x, y, width, height) = layout.playfield;
+    d.draw_rectangle(x, y, width, height, Color::new(0, 0, 0, 100));
+    let font_size = layout.px(48);
+    let paused = text(settings.language, Text::Paused);
+    let text_width = raylib::core::text::measure_text(paused, font_size);
+    d.draw_text(paused, x + width/2 - text_width/2, y + height/2 - font_size/2, font_size, Color::WHITE);
+}
+
+fn draw_achievements(d: &mut impl RaylibDraw, game_state: &GameState, layout: &Layout, settings: &Settings) {
+    let (playfield_x, playfield_y, playfield_width, playfield_height) = layout.playfield;
+    let margin = layout.px(32);
+    let x = playfield_x + margin + layout.px(20);
+    let mut y = playfield_y + margin;
+    d.draw_rectangle(
+        playfield_x + margin, 
+        y, 
+        playfield_width - margin*2, 
+        playfield_height - margin*2, 
+        Color::new(40, 40, 60, 235)
+    );
+    y += layout.px(20);
+    let title = format!(
+        "{} ({}/{})", 
+        text(settings.language, Text::Achievements),
+        game_state.achievements.len(), 
+        ALL_ACHIEVEMENTS.len()
+    );
+    d.draw_text(&title, x, y, layout.px(32), Color::WHITE);
+    y += layout.px(32 + 20);
+    for info in &ALL_ACHIEVEMENTS {
+        let unlocked = game_state.achievements.contains(&info.achievement);
+        let (name_color, text_color) = if unlocked {
+            (Color::GOLD, Color::WHITE)
+        } else {
+            (Color::GRAY, Color::GRAY)
+        };
+        d.draw_text(info.name, x, y, layout.px(20), name_color);
+        let description = if info.bonus_percent > 0 {
+            format!("{} (+{}% sale value)", info.description, info.bonus_percent)
+        } else {
+            info.description.to_owned()
+        };
+        d.draw_text(&description, x, y + layout.px(24), layout.px(16), text_color);
+        y += layout.px(24 + 16 + 16);
+    }
+    let close_hint = format!(
+        "{} {}", 
+        text(settings.language, Text::PressToClose), 
+        key_names(settings, Action::ToggleAchievements)
+    );
+    d.draw_text(
+        &close_hint, 
+        x, 
+        playfield_y + playfield_height - margin - layout.px(36), 
+        layout.px(16), 
+        Color::LIGHTGRAY
+    );
+}
+
+// Breakdown of the active sale bonuses in the bottom right of the playfield,
+// one line per category
+fn draw_multipliers(d: &mut impl RaylibDraw, game_state: &GameState, layout: &Layout, settings: &Settings) {
+    let multipliers = active_multipliers(game_state);
+    if multipliers.is_empty() {
+        return;
+    }
+    let mut lines = vec![text(settings.language, Text::Multipliers).to_string()];
+    for multiplier in &multipliers {
+        let label = match multiplier.category {
+            MultiplierCategory::AllSales => text(settings.language, Text::AllSales).to_string(),
+            MultiplierCategory::Achievements => text(settings.language, Text::Achievements).to_string(),
+            MultiplierCategory::Boost => text(settings.language, Text::GoldenBoost).to_string(),
+            MultiplierCategory::Sides(sides) => shape_name(sides),
+            MultiplierCategory::Color(color) => color_name(color).unwrap_or("Unknown").to_string(),
+        };
+        let factor = (100 + multiplier.bonus_percent) as f64 / 100f64;
+        lines.push(format!("{} x{}", label, factor));
+    }
+    let (playfield_x, playfield_y, playfield_width, playfield_height) = layout.playfield;
+    let margin = layout.px(16);
+    let padding = layout.px(8);
+    let font_size = layout.px(16);
+    let line_height = layout.px(20);
+    let width = lines.iter().map(|l| raylib::core::text::measure_text(l, font_size)).max().unwrap_or(0) + padding * 2;
+    let height = line_height * lines.len() as i32 + padding * 2 - (line_height - font_size);
+    let x = playfield_x + playfield_width - margin - width;
+    let y = playfield_y + playfield_height - margin - height;
+    d.draw_rectangle(x, y, width, height, Color::new(40, 40, 60, 200));
+    for (i, line) in lines.iter().enumerate() {
+        let color = if i == 0 { Color::GOLD } else { Color::WHITE };
+        d.draw_text(line, x + padding, y + padding + line_height * i as i32, font_size, color);
+    }
+}
+
+fn draw_pipe_panel(d: &mut impl RaylibDraw, game_state: &GameState, layout: &Layout, settings: &Settings) {
+    let (pipe, (x, y, width, height)) = match (game_state.pipe_panel, pipe_panel_rect(game_state, layout)) {
+        (Some(pipe), Some(rect)) => (pipe, rect),
+        _ => return,
+    };
+    let stats = pipe_stats(game_state, pipe);
+    let padding = layout.px(12);
+    let font_size = layout.px(16);
+    d.draw_rectangle(x, y, width, height, Color::new(40, 40, 60, 220));
+    let title = format!("{} {}: {}", text(settings.language, Text::Pipe), pipe + 1, shape_name(game_state.pipes[pipe].sides));
+    d.draw_text(&title, x + padding, y + padding, layout.px(20), Color::GOLD);
+    let (close_x, close_y, close_size, _) = layout.pipe_panel_close_rect();
+    d.draw_rectangle(close_x, close_y, close_size, close_size, Color::new(90, 90, 130, 255));
+    d.draw_text("x", close_x + close_size/2 - layout.px(4), close_y + layout.px(1), font_size, Color::WHITE);
+    let drops = format!("{}: {:.2}", text(settings.language, Text::DropsPerSecond), stats.drops_per_second);
+    d.draw_text(&drops, x + padding, y + layout.px(40), font_size, Color::WHITE);
+    let share = format!("{}: {}%", text(settings.language, Text::IncomeShare), (stats.income_share * 100f64).round());
+    d.draw_text(&share, x + padding, y + layout.px(60), font_size, Color::WHITE);
+    for (row, color) in stats.colors.iter().enumerate() {
+        let (box_x, box_y, size, _) = layout.pipe_panel_toggle_rect(row);
+        let text_y = box_y + (size - font_size) / 2;
+        d.draw_rectangle_lines(box_x, box_y, size, size, Color::WHITE);
+        if color.enabled {
+            d.draw_rectangle(box_x + layout.px(4), box_y + layout.px(4), size - layout.px(8), size - layout.px(8), color.color);
+        }
+        let name = color_name(color.color).unwrap_or("Unknown");
+        d.draw_text(name, box_x + size + layout.px(8), text_y, font_size, Color::WHITE);
+        for (delta, label) in [(-1, "-"), (1, "+")] {
+            let (button_x, button_y, size, _) = layout.pipe_panel_weight_rect(row, delta);
+            d.draw_rectangle(button_x, button_y, size, size, Color::new(90, 90, 130, 255));
+            d.draw_text(label, button_x + size/2 - layout.px(4), text_y, font_size, Color::WHITE);
+        }
+        let (minus_x, _, size, _) = layout.pipe_panel_weight_rect(row, -1);
+        d.draw_text(&color.weight.to_string(), minus_x + size + layout.px(8), text_y, font_size, Color::WHITE);
+        let rate = format!("{:.2}/s", color.shapes_per_second);
+        let rate_width = raylib::core::text::measure_text(&rate, font_size);
+        d.draw_text(&rate, x + width - padding - rate_width, text_y, font_size, Color::LIGHTGRAY);
     }
 }
 
-const NUM_ABBREVIATIONS: [(usize, &'static str, &'static str); 22] = [
+// Stats of the last clicked shape, next to where it was clicked
+fn draw_inspection(d: &mut impl RaylibDraw, game_state: &GameState, layout: &Layout, settings: &Settings) {
+    let inspection = match &game_state.inspection {
+        Some(inspection) => inspection,
+        None => return,
+    };
+    let lines = [
+        format!("{} {}", color_name(inspection.color).unwrap_or("Unknown"), shape_name(inspection.sides)),
+        format!("{}: {}", text(settings.language, Text::Worth), format_num(&inspection.price.to_bigint(), settings.notation, true)),
+        format!("{}: {}%", text(settings.language, Text::Area), inspection.area_percent),
+        format!("{}: {}", text(settings.language, Text::LaserCuts), inspection.laser_cuts),
+    ];
+    let alpha = (inspection.remaining as f32 / 500f32).min(1f32);
+    let padding = layout.px(8);
+    let font_size = layout.px(16);
+    let line_height = layout.px(20);
+    let width = lines.iter().map(|l| raylib::core::text::measure_text(l, font_size)).max().unwrap_or(0) + padding * 2;
+    let height = line_height * lines.len() as i32 + padding * 2 - (line_height - font_size);
+    let (playfield_x, playfield_y, playfield_width, playfield_height) = layout.playfield;
+    let (x, y) = layout.world_to_screen(inspection.x, inspection.y);
+    let x = (x + layout.px(24)).clamp(playfield_x, (playfield_x + playfield_width - width).max(playfield_x));
+    let y = (y - height / 2).clamp(playfield_y, (playfield_y + playfield_height - height).max(playfield_y));
+    d.draw_rectangle(x, y, width, height, Color::new(40, 40, 60, 220).fade(alpha));
+    for (i, line) in lines.iter().enumerate() {
+        let color = if i == 0 { Color::GOLD } else { Color::WHITE };
+        d.draw_text(line, x + padding, y + padding + line_height * i as i32, font_size, color.fade(alpha));
+    }
+}
+
+fn draw_achievement_notifications(d: &mut impl RaylibDraw, game_state: &GameState, layout: &Layout, settings: &Settings) {
+    let margin = layout.px(16);
+    let width = layout.px(300);
+    let height = layout.px(56);
+    let mut y = layout.height - margin - height;
+    for notification in &game_state.achievement_notifications {
+        let info = achievement_info(notification.achievement);
+        let alpha = (notification.remaining as f32 / 500f32).min(1f32);
+        let text_x = margin + layout.px(12);
+        d.draw_rectangle(margin, y, width, height, Color::new(40, 40, 60, 235).fade(alpha));
+        d.draw_text(text(settings.language, Text::AchievementUnlocked), text_x, y + layout.px(8), layout.px(16), Color::GOLD.fade(alpha));
+        d.draw_text(info.name, text_x, y + layout.px(8 + 20), layout.px(20), Color::WHITE.fade(alpha));
+        y -= height + layout.px(8);
+    }
+}
+
+fn draw_settings(d: &mut impl RaylibDraw, layout: &Layout, settings: &Settings, menu: &SettingsMenu) {
+    let (playfield_x, playfield_y, playfield_width, playfield_height) = layout.playfield;
+    let margin = layout.px(32);
+    let language = settings.language;
+    d.draw_rectangle(
+        playfield_x + margin, 
+        playfield_y + margin, 
+        playfield_width - margin*2, 
+        playfield_height - margin*2, 
+        Color::new(40, 40, 60, 245)
+    );
+    let title_y = playfield_y + margin + layout.px(20);
+    d.draw_text(text(language, Text::Settings), playfield_x + margin + layout.px(20), title_y, layout.px(32), Color::WHITE);
+    for (i, row) in settings_rows().into_iter().enumerate() {
+        let (x, y, width, height) = settings_row_rect(layout, i);
+        if i == menu.selected {
+            d.draw_rectangle(x, y, width, height, Color::new(90, 90, 130, 255));
+        }
+        let (label, value) = match row {
+            SettingsRow::FpsCap => (
+                text(language, Text::FpsCap).to_owned(),
+                match settings.fps_cap {
+                    0 => text(language, Text::Unlimited).to_owned(),
+                    fps => fps.to_string(),
+                }
+            ),
+            SettingsRow::VSync => (
+                text(language, Text::VSync).to_owned(),
+                text(language, if settings.vsync { Text::On } else { Text::Off }).to_owned()
+            ),
+            SettingsRow::Notation => (
+                text(language, Text::Notation).to_owned(),
+                text(language, match settings.notation {
+                    Notation::Named => Text::NotationNamed,
+                    Notation::Abbreviated => Text::NotationAbbreviated,
+                    Notation::Scientific => Text::NotationScientific,
+                }).to_owned()
+            ),
+            SettingsRow::Language => (
+                text(language, Text::Language).to_owned(),
+                language_name(language).to_owned()
+            ),
+            SettingsRow::ParticleDensity => (
+                text(language, Text::ParticleDensity).to_owned(),
+                format_percent(settings.particle_density)
+            ),
+            SettingsRow::Autosave => (
+                text(language, Text::Autosave).to_owned(),
+                match settings.autosave_interval {
+                    0 => text(language, Text::Off).to_owned(),
+                    seconds => format!("{}s", seconds),
+                }
+            ),
+            SettingsRow::MasterVolume => (
+                text(language, Text::MasterVolume).to_owned(),
+                format_percent(settings.master_volume)
+            ),
+            SettingsRow::SoundVolume => (
+                text(language, Text::SoundVolume).to_owned(),
+                format_percent(settings.sound_volume)
+            ),
+            SettingsRow::MusicVolume => (
+                text(language, Text::MusicVolume).to_owned(),
+                format_percent(settings.music_volume)
+            ),
+            SettingsRow::Binding(action) => (
+                format!("{}: {}", text(language, Text::KeyBindings), action_label(action)),
+                if menu.rebinding == Some(action) {
+                    text(language, Text::PressAKey).to_owned()
+                } else {
+                    key_names(settings, action)
+                }
+            ),
+        };
+        let text_y = y + (height - layout.px(16)) / 2;
+        d.draw_text(&label, x + layout.px(20), text_y, layout.px(16), Color::WHITE);
+        let value_width = raylib::core::text::measure_text(&value, layout.px(16));
+        d.draw_text(&value, x + width - layout.px(20) - value_width, text_y, layout.px(16), Color::LIGHTGRAY);
+    }
+}
+
+fn format_percent(fraction: f32) -> String {
+    format!("{}%", (fraction * 100f32).round())
+}
+
+fn action_label(action: Action) -> &'static str {
+    match action {
+        Action::SelectPrevious => "Previous",
+        Action::SelectNext => "Next",
+        Action::BuySelected => "Buy / Confirm",
+        Action::ScrollShop(rows) if rows < 0 => "Scroll up",
+        Action::ScrollShop(_) => "Scroll down",
+        Action::Adjust(delta) if delta < 0 => "Decrease",
+        Action::Adjust(_) => "Increase",
+        Action::TogglePause => "Pause",
+        Action::ToggleAchievements => "Achievements",
+        Action::ToggleSettings => "Settings",
+        Action::ToggleFullscreen => "Fullscreen",
+        Action::Buy(_) => "Buy",
+        Action::BuyLevels(..) => "Buy levels",
+        Action::Grab(..) => "Grab",
+        Action::Drag(..) => "Drag",
+        Action::Click(..) => "Click",
+        Action::ClosePipePanel => "Close pipe",
+        Action::TogglePipeColor(_) => "Toggle color",
+        Action::AdjustPipeMix(..) => "Adjust mix",
+        Action::StartSegment(..) | Action::FinishSegment(..) => "Place bumper / ramp",
+    }
+}
+
+fn key_names(settings: &Settings, action: Action) -> String {
+    settings.bindings.keys_for(action)
+        .into_iter()
+        .map(key_name)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+const NUM_ABBREVIATIONS: [(usize, &str, &str); 22] = [
     (66, "Unvigintillion", "c"),
     (63, "Vigintillion", "v"),
     (60, "Novemdecillion", "N"),
@@ -98,12 +599,20 @@ const NUM_ABBREVIATIONS: [(usize, &'static str, &'static str); 22] = [
     (3, "Thousand", "K"),
 ];
 
-fn format_score(state: &GameState) -> String {
-    format!("Score: {}", format_num(&state.score, false))
+fn format_score(state: &GameState, settings: &Settings) -> String {
+    format!(
+        "{}: {}", 
+        text(settings.language, Text::Score), 
+        format_num(&state.score.to_bigint(), settings.notation, false)
+    )
 }
 
-fn format_num(num: &BigInt, short: bool) -> String {
+pub fn format_num(num: &BigInt, notation: Notation, short: bool) -> String {
     let raw_num_str = num.to_str_radix(10);
+    if notation == Notation::Scientific {
+        return format_scientific(&raw_num_str);
+    }
+    let short = short || notation == Notation::Abbreviated;
     for abbr in NUM_ABBREVIATIONS {
         if raw_num_str.len() > abbr.0 + 1 {
             let abbr_text = match short {
@@ -119,5 +628,12 @@ fn format_num(num: &BigInt, short: bool) -> String {
             }
         }
     }
-    return raw_num_str;
-}
\ No newline at end of file
+    raw_num_str
+}
+
+fn format_scientific(raw_num_str: &str) -> String {
+    if raw_num_str.len() <= 6 || raw_num_str.starts_with('-') {
+        return raw_num_str.to_owned();
+    }
+    format!("{}.{}e{}", &raw_num_str[..1], &raw_num_str[1..3], raw_num_str.len() - 1)
+}
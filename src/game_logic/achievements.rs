@@ -0,0 +1,156 @@
+use super::{colors::SHAPE_COLORS, events::GameEvent, score::Score, state::GameState, upgrades::Upgrade};
+
+const NOTIFICATION_DURATION: u64 = 4000;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Achievement {
+    FirstSale,
+    FirstHexagonSold,
+    AllPipes,
+    AllColorsOnPipe,
+    MillionScore,
+    DecillionScore,
+    LaserPurchased,
+}
+
+pub struct AchievementInfo {
+    pub achievement: Achievement,
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    // Permanent bonus to the value of every sold shape
+    pub bonus_percent: u32,
+}
+
+#[derive(Clone)]
+pub struct AchievementNotification {
+    pub achievement: Achievement,
+    pub remaining: u64,
+}
+
+pub const ALL_ACHIEVEMENTS: [AchievementInfo; 7] = [
+    AchievementInfo {
+        achievement: Achievement::FirstSale,
+        id: "first_sale",
+        name: "Open for Business",
+        description: "Sell your first shape",
+        bonus_percent: 0,
+    },
+    AchievementInfo {
+        achievement: Achievement::FirstHexagonSold,
+        id: "first_hexagon_sold",
+        name: "Bestagon",
+        description: "Sell a hexagon",
+        bonus_percent: 2,
+    },
+    AchievementInfo {
+        achievement: Achievement::AllPipes,
+        id: "all_pipes",
+        name: "Full Plumbing",
+        description: "Unlock every pipe",
+        bonus_percent: 2,
+    },
+    AchievementInfo {
+        achievement: Achievement::AllColorsOnPipe,
+        id: "all_colors_on_pipe",
+        name: "Rainbow",
+        description: "Unlock every color on a single pipe",
+        bonus_percent: 3,
+    },
+    AchievementInfo {
+        achievement: Achievement::MillionScore,
+        id: "million_score",
+        name: "Millionaire",
+        description: "Reach a score of 1 Million",
+        bonus_percent: 1,
+    },
+    AchievementInfo {
+        achievement: Achievement::DecillionScore,
+        id: "decillion_score",
+        name: "Decillionaire",
+        description: "Reach a score of 1 Decillion",
+        bonus_percent: 5,
+    },
+    AchievementInfo {
+        achievement: Achievement::LaserPurchased,
+        id: "laser_purchased",
+        name: "Pew Pew",
+        description: "Buy the shape cutting laser",
+        bonus_percent: 2,
+    },
+];
+
+pub fn achievement_info(achievement: Achievement) -> &'static AchievementInfo {
+    ALL_ACHIEVEMENTS.iter().find(|a| a.achievement == achievement).unwrap()
+}
+
+pub fn achievement_from_id(id: &str) -> Option<Achievement> {
+    ALL_ACHIEVEMENTS.iter().find(|a| a.id == id).map(|a| a.achievement)
+}
+
+pub fn achievement_bonus_percent(state: &GameState) -> u32 {
+    state.achievements.iter().map(|a| achievement_info(*a).bonus_percent).sum()
+}
+
+pub fn achievements_tick(state: &mut GameState, dt: u64) {
+    notification_tick(state, dt);
+    let mut earned = vec![];
+    for event in &state.events {
+        match event {
+            GameEvent::ShapeSold { sides, .. } => {
+                earned.push(Achievement::FirstSale);
+                if *sides == 6 {
+                    earned.push(Achievement::FirstHexagonSold);
+                }
+            },
+            GameEvent::UpgradePurchased { upgrade, .. } => {
+                if *upgrade == Upgrade::ShapeLaser(1) {
+                    earned.push(Achievement::LaserPurchased);
+                }
+                if (0..4).any(|pipe| pipe_color_count(state, pipe) == SHAPE_COLORS.len()) {
+                    earned.push(Achievement::AllColorsOnPipe);
+                }
+            },
+            GameEvent::PipeUnlocked(3) => earned.push(Achievement::AllPipes),
+            _ => {}
+        }
+    }
+    if state.score >= Score::from(10u128.pow(6)) {
+        earned.push(Achievement::MillionScore);
+    }
+    if state.score >= Score::from(10u128.pow(33)) {
+        earned.push(Achievement::DecillionScore);
+    }
+    for achievement in earned {
+        unlock_achievement(state, achievement);
+    }
+}
+
+fn unlock_achievement(state: &mut GameState, achievement: Achievement) {
+    if state.achievements.contains(&achievement) {
+        return;
+    }
+    state.achievements.push(achievement);
+    state.achievement_notifications.push(AchievementNotification {
+        achievement,
+        remaining: NOTIFICATION_DURATION,
+    });
+    state.events.push(GameEvent::AchievementUnlocked(achievement));
+}
+
+fn notification_tick(state: &mut GameState, dt: u64) {
+    for notification in &mut state.achievement_notifications {
+        notification.remaining = notification.remaining.saturating_sub(dt);
+    }
+    state.achievement_notifications.retain(|n| n.remaining > 0);
+}
+
+fn pipe_color_count(state: &GameState, pipe: usize) -> usize {
+    state.upgrades.iter().filter(|upg| match upg {
+        Upgrade::Pipe1Color(_) => pipe == 0,
+        Upgrade::Pipe2Color(_) => pipe == 1,
+        Upgrade::Pipe3Color(_) => pipe == 2,
+        Upgrade::Pipe4Color(_) => pipe == 3,
+        _ => false,
+    }).count()
+}
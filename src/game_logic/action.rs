@@ -0,0 +1,217 @@
+use std::{fmt, str::FromStr};
+
+use super::{emission, playfield, state::GameState, upgrades::{purchase_levels, purchase_upgrade}, walls};
+
+// Something the player asked for, independent of the device that produced it.
+// The input layer queues these in `GameState::actions` and `action_tick`
+// consumes them at the start of every tick.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Action {
+    // Buy the upgrade at this index of `available_upgrades`
+    Buy(usize),
+    // Buy this many levels of the speed upgrade at this index in one go
+    BuyLevels(usize, u32),
+    BuySelected,
+    ScrollShop(i32),
+    SelectNext,
+    SelectPrevious,
+    TogglePause,
+    ToggleAchievements,
+    ToggleFullscreen,
+    ToggleSettings,
+    // Change the selected value in a menu, e.g. left/right in the settings
+    Adjust(i32),
+    // Left button pressed, moved and released on the playfield, in world
+    // coordinates
+    Grab(i32, i32),
+    Drag(i32, i32),
+    Click(i32, i32),
+    ClosePipePanel,
+    // Rows of the open pipe panel, one per unlocked color
+    TogglePipeColor(usize),
+    AdjustPipeMix(usize, i32),
+    // Right button pressed and released on the playfield, in world coordinates
+    StartSegment(i32, i32),
+    FinishSegment(i32, i32),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Buy(index) => write!(f, "Buy({})", index),
+            Action::BuyLevels(index, count) => write!(f, "BuyLevels({},{})", index, count),
+            Action::BuySelected => write!(f, "BuySelected"),
+            Action::ScrollShop(rows) => write!(f, "ScrollShop({})", rows),
+            Action::SelectNext => write!(f, "SelectNext"),
+            Action::SelectPrevious => write!(f, "SelectPrevious"),
+            Action::TogglePause => write!(f, "TogglePause"),
+            Action::ToggleAchievements => write!(f, "ToggleAchievements"),
+            Action::ToggleFullscreen => write!(f, "ToggleFullscreen"),
+            Action::ToggleSettings => write!(f, "ToggleSettings"),
+            Action::Adjust(delta) => write!(f, "Adjust({})", delta),
+            Action::Grab(x, y) => write!(f, "Grab({},{})", x, y),
+            Action::Drag(x, y) => write!(f, "Drag({},{})", x, y),
+            Action::Click(x, y) => write!(f, "Click({},{})", x, y),
+            Action::ClosePipePanel => write!(f, "ClosePipePanel"),
+            Action::TogglePipeColor(row) => write!(f, "TogglePipeColor({})", row),
+            Action::AdjustPipeMix(row, delta) => write!(f, "AdjustPipeMix({},{})", row, delta),
+            Action::StartSegment(x, y) => write!(f, "StartSegment({},{})", x, y),
+            Action::FinishSegment(x, y) => write!(f, "FinishSegment({},{})", x, y),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    // Parses the format produced by `Display`, e.g. `ScrollShop(-5)`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.find('(') {
+            Some(open) if s.ends_with(')') => (&s[..open], Some(&s[open + 1..s.len() - 1])),
+            _ => (s, None),
+        };
+        let number = || arg
+            .and_then(|a| a.trim().parse::<i64>().ok())
+            .ok_or(format!("Expected a number in '{}'", s));
+        let point = || arg
+            .and_then(|a| a.split_once(','))
+            .and_then(|(x, y)| Some((x.trim().parse::<i32>().ok()?, y.trim().parse::<i32>().ok()?)))
+            .ok_or(format!("Expected two numbers in '{}'", s));
+        match name {
+            "Buy" => Ok(Action::Buy(number()?.max(0) as usize)),
+            "BuyLevels" => point().map(|(index, count)| Action::BuyLevels(index.max(0) as usize, count.max(1) as u32)),
+            "BuySelected" => Ok(Action::BuySelected),
+            "ScrollShop" => Ok(Action::ScrollShop(number()? as i32)),
+            "SelectNext" => Ok(Action::SelectNext),
+            "SelectPrevious" => Ok(Action::SelectPrevious),
+            "TogglePause" => Ok(Action::TogglePause),
+            "ToggleAchievements" => Ok(Action::ToggleAchievements),
+            "ToggleFullscreen" => Ok(Action::ToggleFullscreen),
+            "ToggleSettings" => Ok(Action::ToggleSettings),
+            "Adjust" => Ok(Action::Adjust(number()? as i32)),
+            "Grab" => point().map(|(x, y)| Action::Grab(x, y)),
+            "Drag" => point().map(|(x, y)| Action::Drag(x, y)),
+            "Click" => point().map(|(x, y)| Action::Click(x, y)),
+            "ClosePipePanel" => Ok(Action::ClosePipePanel),
+            "TogglePipeColor" => Ok(Action::TogglePipeColor(number()?.max(0) as usize)),
+            "AdjustPipeMix" => point().map(|(row, delta)| Action::AdjustPipeMix(row.max(0) as usize, delta)),
+            "StartSegment" => point().map(|(x, y)| Action::StartSegment(x, y)),
+            "FinishSegment" => point().map(|(x, y)| Action::FinishSegment(x, y)),
+            _ => Err(format!("Unknown action '{}'", s)),
+        }
+    }
+}
+
+pub fn action_tick(state: &mut GameState) {
+    while let Some(action) = state.actions.pop_front() {
+        match action {
+            Action::Buy(_) | Action::BuyLevels(..) | Action::BuySelected | Action::Grab(..) | Action::Drag(..) | Action::StartSegment(..) if state.paused => {},
+            Action::FinishSegment(..) if state.paused => state.segment_start = None,
+            // Don't leave a shape stuck to the mouse across a pause
+            Action::Click(..) if state.paused => { playfield::release(state); },
+            Action::Buy(index) => purchase_upgrade(state, index),
+            Action::BuyLevels(index, count) => purchase_levels(state, index, count as u64),
+            Action::BuySelected => purchase_upgrade(state, state.shop_selection),
+            Action::ScrollShop(rows) => scroll_shop(state, rows),
+            Action::SelectNext => select_upgrade(state, 1),
+            Action::SelectPrevious => select_upgrade(state, -1),
+            Action::TogglePause => state.paused = !state.paused,
+            Action::ToggleAchievements => state.show_achievements = !state.show_achievements,
+            Action::Grab(x, y) => playfield::grab(state, x as f64, y as f64),
+            Action::Drag(x, y) => playfield::drag(state, x as f64, y as f64),
+            Action::Click(x, y) => playfield::click(state, x as f64, y as f64),
+            Action::ClosePipePanel => state.pipe_panel = None,
+            Action::TogglePipeColor(row) => emission::toggle_color(state, row),
+            Action::AdjustPipeMix(row, delta) => emission::adjust_weight(state, row, delta),
+            Action::StartSegment(x, y) => walls::start_segment(state, x as f64, y as f64),
+            Action::FinishSegment(x, y) => walls::finish_segment(state, x as f64, y as f64),
+            // Handled by the window and menus before the tick
+            Action::ToggleFullscreen | Action::ToggleSettings | Action::Adjust(_) => {},
+        }
+    }
+}
+
+fn scroll_shop(state: &mut GameState, rows: i32) {
+    let max_scroll = state.available_upgrades.len().saturating_sub(state.shop_rows);
+    let scroll = (state.shop_scroll as i64 + rows as i64).clamp(0, max_scroll as i64);
+    state.shop_scroll = scroll as usize;
+    // Keep the selection on screen so keyboard and mouse stay in agreement
+    state.shop_selection = state.shop_selection
+        .clamp(state.shop_scroll, state.shop_scroll + state.shop_rows - 1);
+}
+
+fn select_upgrade(state: &mut GameState, delta: i32) {
+    if state.available_upgrades.is_empty() {
+        return;
+    }
+    let last = state.available_upgrades.len() as i64 - 1;
+    state.shop_selection = (state.shop_selection as i64 + delta as i64).clamp(0, last) as usize;
+    if state.shop_selection < state.shop_scroll {
+        state.shop_scroll = state.shop_selection;
+    } else if state.shop_selection >= state.shop_scroll + state.shop_rows {
+        state.shop_scroll = state.shop_selection + 1 - state.shop_rows;
+    }
+}
+
+// Buying shrinks the list, so the cursor may point past the end
+pub fn clamp_shop_cursor(state: &mut GameState) {
+    let len = state.available_upgrades.len();
+    state.shop_selection = state.shop_selection.min(len.saturating_sub(1));
+    state.shop_scroll = state.shop_scroll.min(len.saturating_sub(state.shop_rows));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One of every variant, with negative numbers where they are allowed
+    const ALL_ACTIONS: [Action; 19] = [
+        Action::Buy(12),
+        Action::BuyLevels(4, 10),
+        Action::BuySelected,
+        Action::ScrollShop(-5),
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::TogglePause,
+        Action::ToggleAchievements,
+        Action::ToggleFullscreen,
+        Action::ToggleSettings,
+        Action::Adjust(-1),
+        Action::Grab(10, -20),
+        Action::Drag(300, 400),
+        Action::Click(-1, 0),
+        Action::ClosePipePanel,
+        Action::TogglePipeColor(3),
+        Action::AdjustPipeMix(2, -1),
+        Action::StartSegment(100, 200),
+        Action::FinishSegment(-300, 250),
+    ];
+
+    #[test]
+    fn every_action_parses_back_from_its_text() {
+        for action in ALL_ACTIONS {
+            assert_eq!(Action::from_str(&action.to_string()), Ok(action), "{}", action);
+        }
+    }
+
+    #[test]
+    fn parsing_ignores_surrounding_and_inner_spaces() {
+        assert_eq!(Action::from_str("  Grab( 1 , 2 ) "), Ok(Action::Grab(1, 2)));
+        assert_eq!(Action::from_str("ScrollShop( -3 )"), Ok(Action::ScrollShop(-3)));
+    }
+
+    #[test]
+    fn bad_action_text_is_an_error() {
+        for text in ["", "Jump", "Buy", "Buy(x)", "Grab(1)", "Drag(1,2", "Click(a,b)"] {
+            assert!(Action::from_str(text).is_err(), "'{}' parsed", text);
+        }
+    }
+
+    #[test]
+    fn negative_indices_are_clamped_to_zero() {
+        assert_eq!(Action::from_str("Buy(-4)"), Ok(Action::Buy(0)));
+        assert_eq!(Action::from_str("BuyLevels(-4,0)"), Ok(Action::BuyLevels(0, 1)));
+        assert_eq!(Action::from_str("AdjustPipeMix(-1,1)"), Ok(Action::AdjustPipeMix(0, 1)));
+    }
+}
@@ -0,0 +1,179 @@
+use std::ops::Range;
+
+use rand::Rng;
+use raylib::prelude::Color;
+
+use super::{events::GameEvent, physics::physics, pipe::{drop_point, spin}, shape::regular_shape, state::{GameState, Shape}};
+
+// Milliseconds of game time between golden shapes
+pub const GOLDEN_INTERVAL: Range<u64> = 60_000..180_000;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BoostKind {
+    DropSpeed,
+    SaleValue,
+}
+
+const ALL_BOOSTS: [BoostKind; 2] = [BoostKind::DropSpeed, BoostKind::SaleValue];
+
+// A boost running out with the game clock, so it pauses along with the game
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Boost {
+    pub kind: BoostKind,
+    // Milliseconds
+    pub remaining: u64,
+}
+
+// How strong a boost is and how long it lasts in milliseconds
+pub fn boost_info(kind: BoostKind) -> (u64, u64) {
+    match kind {
+        BoostKind::DropSpeed => (2, 30_000),
+        BoostKind::SaleValue => (7, 10_000),
+    }
+}
+
+pub fn boost_tick(state: &mut GameState, dt: u64) {
+    for boost in &mut state.boosts {
+        boost.remaining = boost.remaining.saturating_sub(dt);
+    }
+    state.boosts.retain(|b| b.remaining > 0);
+
+    state.next_golden = state.next_golden.saturating_sub(dt);
+    if state.next_golden == 0 {
+        state.next_golden = state.rng.gen_range(GOLDEN_INTERVAL);
+        drop_golden_shape(state);
+    }
+}
+
+// Falls from a random pipe like the others, but is only worth clicking
+fn drop_golden_shape(state: &mut GameState) {
+    let pipe = state.rng.gen_range(0..state.pipes.len());
+    let sides = state.pipes[pipe].sides;
+    let (x, y) = drop_point(pipe);
+    let physics = physics(state);
+    state.shapes.push(Shape {
+        vel_x: state.rng.gen_range(-40f64..40f64),
+        vel_y: physics.drop_speed,
+        rot_vel: spin(&mut state.rng, &physics),
+        golden: true,
+        ..regular_shape(sides, Color::GOLD, x, y)
+    });
+}
+
+// Multiplier of the given boost kind, 1 while it isn't running
+pub fn boost_factor(state: &GameState, kind: BoostKind) -> u64 {
+    match state.boosts.iter().any(|b| b.kind == kind) {
+        true => boost_info(kind).0,
+        false => 1,
+    }
+}
+
+// Catches the golden shape under the world position, if any, and starts a
+// random boost. Catching the same kind again restarts its countdown.
+pub fn catch_golden(state: &mut GameState, x: f64, y: f64) -> bool {
+    let hit = state.shapes.iter().rposition(|s| s.golden && s.contains(x, y));
+    let shape = match hit {
+        Some(i) => state.shapes.remove(i),
+        None => return false,
+    };
+    let kind = ALL_BOOSTS[state.rng.gen_range(0..ALL_BOOSTS.len())];
+    let remaining = boost_info(kind).1;
+    match state.boosts.iter_mut().find(|b| b.kind == kind) {
+        Some(boost) => boost.remaining = remaining,
+        None => state.boosts.push(Boost { kind, remaining }),
+    }
+    state.events.push(GameEvent::BoostStarted { kind, x: shape.x, y: shape.y });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{state::seeded_state, tick::tick};
+
+    fn golden_shape(x: f64, y: f64) -> Shape {
+        Shape { golden: true, ..regular_shape(4, Color::GOLD, x, y) }
+    }
+
+    #[test]
+    fn boosts_run_out_with_the_game_clock() {
+        let mut state = seeded_state(1);
+        state.next_golden = u64::MAX;
+        state.boosts = vec![Boost { kind: BoostKind::SaleValue, remaining: 10_000 }];
+        assert_eq!(boost_factor(&state, BoostKind::SaleValue), 7);
+        assert_eq!(boost_factor(&state, BoostKind::DropSpeed), 1);
+        boost_tick(&mut state, 9_990);
+        assert_eq!(boost_factor(&state, BoostKind::SaleValue), 7);
+        boost_tick(&mut state, 10);
+        assert!(state.boosts.is_empty());
+        assert_eq!(boost_factor(&state, BoostKind::SaleValue), 1);
+    }
+
+    #[test]
+    fn paused_games_keep_their_boosts() {
+        let mut state = seeded_state(1);
+        state.paused = true;
+        state.boosts = vec![Boost { kind: BoostKind::DropSpeed, remaining: 30_000 }];
+        for _ in 0..100 {
+            tick(&mut state, 1000);
+        }
+        assert_eq!(state.boosts[0].remaining, 30_000);
+    }
+
+    #[test]
+    fn both_kinds_run_side_by_side() {
+        let mut state = seeded_state(1);
+        state.next_golden = u64::MAX;
+        state.boosts = vec![
+            Boost { kind: BoostKind::DropSpeed, remaining: 30_000 },
+            Boost { kind: BoostKind::SaleValue, remaining: 10_000 },
+        ];
+        boost_tick(&mut state, 10_000);
+        assert_eq!(state.boosts, vec![Boost { kind: BoostKind::DropSpeed, remaining: 20_000 }]);
+        assert_eq!(boost_factor(&state, BoostKind::DropSpeed), 2);
+    }
+
+    #[test]
+    fn catching_the_same_kind_restarts_it_instead_of_stacking() {
+        let mut state = seeded_state(1);
+        state.next_golden = u64::MAX;
+        for _ in 0..20 {
+            state.shapes.push(golden_shape(100f64, 100f64));
+            assert!(catch_golden(&mut state, 100f64, 100f64));
+            // Every boost caught so far is still running, but each kind only once
+            for kind in ALL_BOOSTS {
+                assert!(state.boosts.iter().filter(|b| b.kind == kind).count() <= 1);
+            }
+            let caught = match state.events.last() {
+                Some(GameEvent::BoostStarted { kind, .. }) => *kind,
+                _ => panic!("no boost started"),
+            };
+            let boost = state.boosts.iter().find(|b| b.kind == caught).unwrap();
+            assert_eq!(boost.remaining, boost_info(caught).1);
+            assert_eq!(boost_factor(&state, caught), boost_info(caught).0);
+            boost_tick(&mut state, 1_000);
+        }
+    }
+
+    #[test]
+    fn missing_the_golden_shape_starts_nothing() {
+        let mut state = seeded_state(1);
+        state.shapes.push(golden_shape(100f64, 100f64));
+        state.shapes.push(regular_shape(4, Color::GRAY, 300f64, 100f64));
+        assert!(!catch_golden(&mut state, 300f64, 100f64));
+        assert!(!catch_golden(&mut state, 500f64, 500f64));
+        assert!(state.boosts.is_empty());
+        assert_eq!(state.shapes.len(), 2);
+    }
+
+    #[test]
+    fn a_golden_shape_drops_when_the_timer_runs_out() {
+        let mut state = seeded_state(1);
+        state.next_golden = 50;
+        boost_tick(&mut state, 40);
+        assert!(state.shapes.is_empty());
+        boost_tick(&mut state, 10);
+        assert_eq!(state.shapes.iter().filter(|s| s.golden).count(), 1);
+        assert!(GOLDEN_INTERVAL.contains(&state.next_golden));
+    }
+}
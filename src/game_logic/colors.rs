@@ -0,0 +1,24 @@
+use raylib::prelude::Color;
+
+// Colors a pipe can be upgraded to emit, in unlock order
+pub const SHAPE_COLORS: [(Color, &str); 5] = [
+    (Color::RED, "Red"),
+    (Color::GREEN, "Green"),
+    (Color::BLUE, "Blue"),
+    (Color::YELLOW, "Yellow"),
+    (Color::PURPLE, "Purple"),
+];
+
+pub fn color_name(color: Color) -> Option<&'static str> {
+    if color == Color::GRAY {
+        return Some("Gray");
+    }
+    SHAPE_COLORS.iter().find(|c| c.0 == color).map(|c| c.1)
+}
+
+pub fn color_from_name(name: &str) -> Option<Color> {
+    if name.eq_ignore_ascii_case("Gray") {
+        return Some(Color::GRAY);
+    }
+    SHAPE_COLORS.iter().find(|c| c.1.eq_ignore_ascii_case(name)).map(|c| c.0)
+}
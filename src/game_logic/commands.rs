@@ -0,0 +1,159 @@
+use std::str::FromStr;
+
+use num::{BigInt, FromPrimitive};
+use rand::Rng;
+
+use super::{
+    colors::{color_from_name, color_name},
+    laser::CutMode,
+    prices::price_table,
+    score::Score,
+    shape::regular_shape,
+    state::{GameState, Shape},
+    upgrades::Upgrade,
+    world::WORLD_WIDTH,
+};
+
+// Cheat commands typed into the dev console. They live with the game rules so
+// replays can rerun them, see `ReplayEntry::Command`.
+
+const MAX_SPAWN: u32 = 10_000;
+pub const SHAPE_NAMES: [(&str, i32); 6] = [
+    ("triangle", 3),
+    ("square", 4),
+    ("pentagon", 5),
+    ("hexagon", 6),
+    ("heptagon", 7),
+    ("octagon", 8),
+];
+
+const HELP: [&str; 9] = [
+    "score set <n>          e.g. score set 1e30",
+    "score add <n>",
+    "upgrade grant <upgrade> e.g. upgrade grant Pipe3Speed(20)",
+    "upgrade revoke <upgrade>",
+    "timescale <factor>     e.g. timescale 50",
+    "spawn <shape> <color> [count]",
+    "cutmode add_side|slice what lasers do to shapes",
+    "state dump",
+    "Tab completes, Up/Down browse history",
+];
+
+pub fn run_command(state: &mut GameState, line: &str) -> Result<Vec<String>, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["help"] => Ok(HELP.iter().map(|l| l.to_string()).collect()),
+        ["score", "set", n] => {
+            state.score = parse_number(n)?;
+            Ok(vec![format!("score = {}", state.score)])
+        },
+        ["score", "add", n] => {
+            state.score += &parse_number(n)?;
+            Ok(vec![format!("score = {}", state.score)])
+        },
+        ["upgrade", "grant", upgrade] => {
+            let upgrade = Upgrade::from_str(upgrade)?;
+            if state.upgrades.contains(&upgrade) {
+                return Err(format!("{} is already owned", upgrade));
+            }
+            state.upgrades.push(upgrade.clone());
+            Ok(vec![format!("granted {}", upgrade)])
+        },
+        ["upgrade", "revoke", upgrade] => {
+            let upgrade = Upgrade::from_str(upgrade)?;
+            let before = state.upgrades.len();
+            state.upgrades.retain(|u| *u != upgrade);
+            if state.upgrades.len() == before {
+                return Err(format!("{} is not owned", upgrade));
+            }
+            Ok(vec![format!("revoked {}", upgrade)])
+        },
+        ["timescale", factor] => {
+            let factor: f64 = factor.parse().map_err(|_| format!("bad factor '{}'", factor))?;
+            if !(0f64..=1000f64).contains(&factor) {
+                return Err("the factor must be between 0 and 1000".to_string());
+            }
+            state.time_scale = factor;
+            Ok(vec![format!("timescale = {}", factor)])
+        },
+        ["spawn", shape, color] => spawn(state, shape, color, 1),
+        ["spawn", shape, color, count] => {
+            let count = count.parse().map_err(|_| format!("bad count '{}'", count))?;
+            spawn(state, shape, color, count)
+        },
+        ["cutmode", mode] => {
+            state.cut_mode = match *mode {
+                "add_side" => CutMode::AddSide,
+                "slice" => CutMode::Slice,
+                _ => return Err(format!("unknown cut mode '{}'", mode)),
+            };
+            Ok(vec![format!("cutmode = {}", mode)])
+        },
+        ["state", "dump"] => Ok(dump_state(state)),
+        _ => Err(format!("unknown command '{}', try help", line.trim())),
+    }
+}
+
+// Plain integers or `<mantissa>e<exponent>` like 1e30 or 2.5e12
+fn parse_number(n: &str) -> Result<Score, String> {
+    let bad = || format!("bad number '{}'", n);
+    let (mantissa, exponent) = match n.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<u32>().map_err(|_| bad())?),
+        None => (n, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if fraction.len() as u32 > exponent {
+        return Err(bad());
+    }
+    let digits = BigInt::from_str(&format!("{}{}", whole, fraction)).map_err(|_| bad())?;
+    let scale = BigInt::from_u64(10).unwrap().pow(exponent - fraction.len() as u32);
+    Score::from_bigint(&(digits * scale)).ok_or("the score can't be negative".to_string())
+}
+
+fn spawn(state: &mut GameState, shape: &str, color: &str, count: u32) -> Result<Vec<String>, String> {
+    let sides = SHAPE_NAMES.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(shape))
+        .map(|(_, sides)| *sides)
+        .ok_or(format!("unknown shape '{}'", shape))?;
+    let color = color_from_name(color).ok_or(format!("unknown color '{}'", color))?;
+    price_table().check_color(color)?;
+    if count > MAX_SPAWN {
+        return Err(format!("at most {} shapes at once", MAX_SPAWN));
+    }
+    // The game's generator so replays spawn the same shapes
+    let rng = &mut state.rng;
+    for _ in 0..count {
+        let x = rng.gen_range(40f64..(WORLD_WIDTH - 40) as f64);
+        let y = rng.gen_range(0f64..60f64);
+        state.shapes.push(Shape {
+            vel_x: rng.gen_range(-30f64..30f64),
+            vel_y: 300f64,
+            rot: rng.gen_range(0f32..360f32),
+            rot_vel: 0.1f32,
+            ..regular_shape(sides, color, x, y)
+        });
+    }
+    Ok(vec![format!("spawned {} {}", count, shape)])
+}
+
+fn dump_state(state: &GameState) -> Vec<String> {
+    let mut lines = vec![
+        format!("score: {}", state.score),
+        format!("shapes: {}", state.shapes.len()),
+        format!("timescale: {}", state.time_scale),
+        format!("cutmode: {:?}", state.cut_mode),
+    ];
+    for (i, pipe) in state.pipes.iter().enumerate() {
+        lines.push(format!(
+            "pipe {}: {} sides, {}, speed {}",
+            i + 1,
+            pipe.sides,
+            color_name(pipe.color).unwrap_or("Unknown"),
+            pipe.speed
+        ));
+    }
+    let upgrades: Vec<String> = state.upgrades.iter().map(|u| u.to_string()).collect();
+    lines.push(format!("upgrades ({}): {}", upgrades.len(), upgrades.join(" ")));
+    lines.push(format!("achievements: {}", state.achievements.len()));
+    lines
+}
@@ -0,0 +1,196 @@
+use raylib::prelude::Color;
+
+use super::{boosts::{BoostKind, boost_factor}, multipliers::active_multipliers, prices::price_table, shape::regular_shape, state::GameState};
+use super::pipe::{drop_delay, unlocked_colors};
+
+pub const MAX_EMISSION_WEIGHT: u32 = 9;
+
+// How a pipe splits its drops between the colors it has unlocked. A drop makes
+// one shape per unlocked color and each color gets its weight's share of
+// them, a color switched off drops nothing instead of passing its share on.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ColorEmission {
+    pub pipe: usize,
+    pub color: Color,
+    pub enabled: bool,
+    pub weight: u32,
+    // Leftover share of a shape from earlier drops, in units of the pipe's
+    // total weight
+    pub credit: u64,
+}
+
+pub struct ColorStats {
+    pub color: Color,
+    pub enabled: bool,
+    pub weight: u32,
+    pub shapes_per_second: f64,
+}
+
+pub struct PipeStats {
+    pub drops_per_second: f64,
+    pub colors: Vec<ColorStats>,
+    // Of the income of all pipes, from 0 to 1, before lasers
+    pub income_share: f64,
+}
+
+// Adds an even share for colors unlocked since the last call
+pub fn sync_emission(state: &mut GameState) {
+    for pipe in 0..state.pipes.len() {
+        for color in unlocked_colors(state, pipe as i32) {
+            if !state.emission.iter().any(|e| e.pipe == pipe && e.color == color) {
+                state.emission.push(ColorEmission { pipe, color, enabled: true, weight: 1, credit: 0 });
+            }
+        }
+    }
+}
+
+// The colors of the shapes in one drop of up to `count`, fewer with colors
+// switched off. Leftover shares carry over to the next drop so the mix comes
+// out right over time, an even mix gives one shape of each enabled color
+// every drop.
+pub fn drop_colors(state: &mut GameState, pipe: usize, count: u64) -> Vec<Color> {
+    let total: u64 = pipe_emission(state, pipe).map(|e| e.weight as u64).sum();
+    let mut result = vec![];
+    if total == 0 {
+        return result;
+    }
+    for entry in state.emission.iter_mut().filter(|e| e.pipe == pipe && e.enabled) {
+        entry.credit += count * entry.weight as u64;
+        for _ in 0..entry.credit / total {
+            result.push(entry.color);
+        }
+        entry.credit %= total;
+    }
+    result
+}
+
+pub fn pipe_emission(state: &GameState, pipe: usize) -> impl Iterator<Item = &ColorEmission> {
+    state.emission.iter().filter(move |e| e.pipe == pipe)
+}
+
+// `row` counts the open pipe's colors in the order of `GameState::emission`,
+// like the pipe panel.
+// The last enabled color can't be turned off.
+pub fn toggle_color(state: &mut GameState, row: usize) {
+    let pipe = match state.pipe_panel {
+        Some(pipe) => pipe,
+        None => return,
+    };
+    let enabled = pipe_emission(state, pipe).filter(|e| e.enabled).count();
+    if let Some(entry) = state.emission.iter_mut().filter(|e| e.pipe == pipe).nth(row) {
+        if entry.enabled && enabled == 1 {
+            return;
+        }
+        entry.enabled = !entry.enabled;
+        entry.credit = 0;
+    }
+}
+
+pub fn adjust_weight(state: &mut GameState, row: usize, delta: i32) {
+    let pipe = match state.pipe_panel {
+        Some(pipe) => pipe,
+        None => return,
+    };
+    if let Some(entry) = state.emission.iter_mut().filter(|e| e.pipe == pipe).nth(row) {
+        entry.weight = (entry.weight as i64 + delta as i64).clamp(1, MAX_EMISSION_WEIGHT as i64) as u32;
+    }
+}
+
+pub fn pipe_stats(state: &GameState, pipe: usize) -> PipeStats {
+    let colors = color_stats(state, pipe);
+    let multipliers = active_multipliers(state);
+    let income = |pipe: usize, colors: &[ColorStats]| -> f64 {
+        colors.iter()
+            .map(|c| {
+                let shape = regular_shape(state.pipes[pipe].sides, c.color, 0f64, 0f64);
+                let price = price_table().sale_price(&shape, &multipliers).map(|p| p.to_f64()).unwrap_or(0f64);
+                c.shapes_per_second * price
+            })
+            .sum()
+    };
+    let own = income(pipe, &colors);
+    let total: f64 = (0..state.pipes.len()).map(|i| income(i, &color_stats(state, i))).sum();
+    PipeStats {
+        drops_per_second: drops_per_second(state, pipe),
+        colors,
+        income_share: if total > 0f64 { own / total } else { 0f64 },
+    }
+}
+
+fn drops_per_second(state: &GameState, pipe: usize) -> f64 {
+    1000f64 / drop_delay(&state.pipes[pipe]) as f64 * boost_factor(state, BoostKind::DropSpeed) as f64
+}
+
+fn color_stats(state: &GameState, pipe: usize) -> Vec<ColorStats> {
+    let drops_per_second = drops_per_second(state, pipe);
+    let shapes_per_drop = pipe_emission(state, pipe).count() as f64;
+    let total: u32 = pipe_emission(state, pipe).map(|e| e.weight).sum();
+    pipe_emission(state, pipe)
+        .map(|e| ColorStats {
+            color: e.color,
+            enabled: e.enabled,
+            weight: e.weight,
+            shapes_per_second: match e.enabled && total > 0 {
+                true => drops_per_second * shapes_per_drop * e.weight as f64 / total as f64,
+                false => 0f64,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::state::seeded_state;
+
+    fn state_with(colors: &[(Color, u32)]) -> GameState {
+        let mut state = seeded_state(0);
+        state.emission = colors.iter()
+            .map(|(color, weight)| ColorEmission { pipe: 0, color: *color, enabled: true, weight: *weight, credit: 0 })
+            .collect();
+        state
+    }
+
+    #[test]
+    fn leftover_shares_carry_over() {
+        let mut state = state_with(&[(Color::GRAY, 2), (Color::RED, 1)]);
+        assert_eq!(drop_colors(&mut state, 0, 2), vec![Color::GRAY]);
+        assert_eq!(drop_colors(&mut state, 0, 2), vec![Color::GRAY, Color::RED]);
+        assert_eq!(drop_colors(&mut state, 0, 2), vec![Color::GRAY, Color::GRAY, Color::RED]);
+        // Every share used up after a whole cycle
+        assert!(state.emission.iter().all(|e| e.credit == 0));
+    }
+
+    #[test]
+    fn colors_switched_off_drop_nothing() {
+        let mut state = state_with(&[(Color::GRAY, 1), (Color::RED, 1), (Color::PURPLE, 1)]);
+        assert_eq!(drop_colors(&mut state, 0, 3), vec![Color::GRAY, Color::RED, Color::PURPLE]);
+        state.emission[0].enabled = false;
+        state.emission[1].enabled = false;
+        for _ in 0..10 {
+            assert_eq!(drop_colors(&mut state, 0, 3), vec![Color::PURPLE]);
+        }
+        // No colors on another pipe
+        assert_eq!(drop_colors(&mut state, 1, 3), vec![]);
+    }
+
+    #[test]
+    fn the_last_color_stays_on() {
+        let mut state = state_with(&[(Color::GRAY, 1), (Color::RED, 1)]);
+        toggle_color(&mut state, 0);
+        assert!(state.emission[0].enabled, "no pipe panel open");
+        state.pipe_panel = Some(0);
+        state.emission[0].credit = 1;
+        toggle_color(&mut state, 0);
+        assert!(!state.emission[0].enabled);
+        assert_eq!(state.emission[0].credit, 0);
+        toggle_color(&mut state, 1);
+        assert!(state.emission[1].enabled);
+        toggle_color(&mut state, 0);
+        toggle_color(&mut state, 1);
+        assert!(state.emission[0].enabled && !state.emission[1].enabled);
+        // Rows past the pipe's colors do nothing
+        toggle_color(&mut state, 2);
+        assert!(state.emission[0].enabled && !state.emission[1].enabled);
+    }
+}
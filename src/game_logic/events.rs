@@ -0,0 +1,18 @@
+use raylib::prelude::Color;
+
+use super::{achievements::Achievement, boosts::BoostKind, score::Score, upgrades::Upgrade};
+
+// Things that happened during the last tick. Cleared at the start of every
+// tick so other systems can react to them without polling the state.
+#[derive(Clone)]
+pub enum GameEvent {
+    ShapeSold { sides: i32, color: Color, price: Score, x: f64, y: f64 },
+    ShapesDropped { sides: i32 },
+    ShapeCut { sides: i32, x: f64, y: f64 },
+    // `index` is the position the upgrade had in `available_upgrades`
+    UpgradePurchased { upgrade: Upgrade, index: usize },
+    PipeUnlocked(usize),
+    AchievementUnlocked(Achievement),
+    // A golden shape was clicked at `x`, `y`
+    BoostStarted { kind: BoostKind, x: f64, y: f64 },
+}
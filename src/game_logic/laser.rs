@@ -0,0 +1,439 @@
+use super::{prices::MAX_SIDES, shape::polygon_area, state::{GameState, Shape}, upgrades::{Upgrade, unlock_upgrades::all_upgrades}, world::{WORLD_HEIGHT, WORLD_WIDTH}};
+
+// Speed in world units per second at which the two halves of a sliced shape
+// drift apart
+const SLICE_SEPARATION: f64 = 40f64;
+// Pieces smaller than this are not worth splitting off
+const MIN_PIECE_AREA: f64 = 1f64;
+// Each laser needs a bit in `Shape::laser_cuts`
+const MAX_LASERS: usize = 32;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CutMode {
+    // The shape turns into a regular polygon with one more side
+    AddSide,
+    // The shape is split along the beam into two convex pieces
+    Slice,
+}
+
+// A laser is a thick line segment across the playfield. It cuts every shape
+// passing it once, how depends on the `CutMode`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Laser {
+    // Horizontal extent in world units
+    pub min_x: f64,
+    pub max_x: f64,
+    // Height of the midpoint
+    pub y: f64,
+    // Tilt around the midpoint in degrees, clockwise on screen
+    pub angle: f64,
+    pub thickness: f64,
+}
+
+// Every laser in the game with the `ShapeLaser` level that turns it on, unless
+// assets/lasers.txt lists others. The position in `GameState::lasers` is the
+// bit a cut sets in `Shape::laser_cuts`.
+pub const DEFAULT_LASERS: [(u64, Laser); 1] = [
+    (1, Laser {
+        min_x: 0f64,
+        max_x: WORLD_WIDTH as f64,
+        y: (WORLD_HEIGHT / 2 + 150) as f64 + 3.5,
+        angle: 0f64,
+        thickness: 7f64,
+    }),
+];
+
+// Lasers switched on by the upgrades bought so far, with their bit
+pub fn active_lasers(state: &GameState) -> Vec<(u32, Laser)> {
+    let mut result = vec![];
+    for (i, (level, laser)) in state.lasers.iter().enumerate() {
+        if state.upgrades.contains(&Upgrade::ShapeLaser(*level)) {
+            result.push((1 << i, *laser));
+        }
+    }
+    result
+}
+
+// The lasers in `contents`, one `<level> <min_x> <max_x> <y> <angle>
+// <thickness>` per line, see assets/lasers.txt. Bad lines are reported and
+// skipped like the upgrade overrides, without any good ones the defaults stay.
+// Levels without a ShapeLaser upgrade in the catalog could never switch on.
+pub fn load_lasers(contents: &str) -> Vec<(u64, Laser)> {
+    let catalog = all_upgrades();
+    let reachable = |level| catalog.iter().any(|u| u.upgrade == Upgrade::ShapeLaser(level));
+    let mut lasers = vec![];
+    for line in contents.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        match parse_laser(line) {
+            Ok((level, _)) if !reachable(level) => {
+                eprintln!("Ignoring laser '{}': no ShapeLaser({}) upgrade to switch it on", line, level);
+            },
+            Ok(_) if lasers.len() == MAX_LASERS => eprintln!("Ignoring laser '{}': at most {} lasers", line, MAX_LASERS),
+            Ok(laser) => lasers.push(laser),
+            Err(e) => eprintln!("Ignoring laser '{}': {}", line, e),
+        }
+    }
+    if lasers.is_empty() {
+        return DEFAULT_LASERS.to_vec();
+    }
+    lasers
+}
+
+fn parse_laser(line: &str) -> Result<(u64, Laser), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (level, numbers) = match words.split_first() {
+        Some((level, numbers)) if numbers.len() == 5 => (level, numbers),
+        _ => return Err("expected level min_x max_x y angle thickness".to_string()),
+    };
+    let level: u64 = level.parse().map_err(|_| format!("bad level '{}'", level))?;
+    let numbers = numbers.iter()
+        .map(|n| n.parse::<f64>().ok().filter(|n| n.is_finite()).ok_or(format!("bad number '{}'", n)))
+        .collect::<Result<Vec<f64>, String>>()?;
+    let laser = Laser { min_x: numbers[0], max_x: numbers[1], y: numbers[2], angle: numbers[3], thickness: numbers[4] };
+    if level == 0 {
+        return Err("levels start at 1".to_string());
+    }
+    if laser.min_x >= laser.max_x {
+        return Err("min_x must be left of max_x".to_string());
+    }
+    if laser.angle.abs() >= 90f64 {
+        return Err("the angle must be between -90 and 90".to_string());
+    }
+    if laser.thickness <= 0f64 {
+        return Err("the thickness must be above 0".to_string());
+    }
+    Ok((level, laser))
+}
+
+// The lines `load_lasers` reads back into the same lasers
+pub fn lasers_text(lasers: &[(u64, Laser)]) -> String {
+    lasers.iter()
+        .map(|(level, l)| format!("{} {} {} {} {} {}\n", level, l.min_x, l.max_x, l.y, l.angle, l.thickness))
+        .collect()
+}
+
+impl Laser {
+    pub fn endpoints(&self) -> ((f64, f64), (f64, f64)) {
+        let half_rise = (self.max_x - self.min_x) / 2f64 * self.angle.to_radians().tan();
+        ((self.min_x, self.y - half_rise), (self.max_x, self.y + half_rise))
+    }
+
+    fn corners(&self) -> Vec<(f64, f64)> {
+        let ((x1, y1), (x2, y2)) = self.endpoints();
+        let (nx, ny) = normal(x2 - x1, y2 - y1);
+        let (ox, oy) = (nx * self.thickness / 2f64, ny * self.thickness / 2f64);
+        vec![(x1 + ox, y1 + oy), (x2 + ox, y2 + oy), (x2 - ox, y2 - oy), (x1 - ox, y1 - oy)]
+    }
+}
+
+// Whether the shape touched the laser anywhere on its way from its previous
+// position to the current one. The swept area of a translated convex polygon
+// is the hull of its start and end copies, so a separating axis test between
+// that hull and the laser's rectangle is exact at any speed. Rotation within a
+// step is ignored, shapes turn a fraction of a degree per step.
+pub fn swept_hit(laser: &Laser, shape: &Shape) -> bool {
+    let start = shape.corners_at(shape.prev_x, shape.prev_y);
+    let end = shape.corners_at(shape.x, shape.y);
+    let swept: Vec<(f64, f64)> = start.iter().chain(end.iter()).copied().collect();
+    let corners = laser.corners();
+
+    let mut axes = vec![];
+    for i in 0..end.len() {
+        let (x1, y1) = end[i];
+        let (x2, y2) = end[(i + 1) % end.len()];
+        axes.push(normal(x2 - x1, y2 - y1));
+    }
+    let (dx, dy) = (shape.x - shape.prev_x, shape.y - shape.prev_y);
+    if dx != 0f64 || dy != 0f64 {
+        axes.push(normal(dx, dy));
+    }
+    let ((x1, y1), (x2, y2)) = laser.endpoints();
+    axes.push(normal(x2 - x1, y2 - y1));
+    axes.push(normal(y2 - y1, x1 - x2));
+
+    axes.iter().all(|axis| {
+        let (min_a, max_a) = project(&swept, *axis);
+        let (min_b, max_b) = project(&corners, *axis);
+        max_a >= min_b && max_b >= min_a
+    })
+}
+
+// Where the shape's center crossed the laser's line during the last step, if
+// that point lies within the laser
+pub fn center_crossing(laser: &Laser, shape: &Shape) -> Option<(f64, f64)> {
+    let ((x1, y1), (x2, y2)) = laser.endpoints();
+    let (nx, ny) = normal(x2 - x1, y2 - y1);
+    let before = (shape.prev_x - x1) * nx + (shape.prev_y - y1) * ny;
+    let after = (shape.x - x1) * nx + (shape.y - y1) * ny;
+    if !(before <= 0f64 && after > 0f64 || before >= 0f64 && after < 0f64) {
+        return None;
+    }
+    let t = before / (before - after);
+    let x = shape.prev_x + (shape.x - shape.prev_x) * t;
+    let y = shape.prev_y + (shape.y - shape.prev_y) * t;
+    let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+    let along = ((x - x1) * (x2 - x1) + (y - y1) * (y2 - y1)) / length;
+    if !(0f64..=length).contains(&along) {
+        return None;
+    }
+    Some((x, y))
+}
+
+// Splits the shape along the laser's line at the moment its center was at
+// `x`, `y`. Each piece keeps the shape's velocity plus a push away from the
+// beam and carries on from where the shape is now. None when a piece would be
+// too thin to matter.
+pub fn slice(laser: &Laser, shape: &Shape, x: f64, y: f64) -> Option<Vec<Shape>> {
+    let ((x1, y1), (x2, y2)) = laser.endpoints();
+    let (nx, ny) = normal(x2 - x1, y2 - y1);
+    let side = |(px, py): (f64, f64)| (px - x) * nx + (py - y) * ny;
+    let corners = shape.corners_at(x, y);
+    let mut pieces = vec![];
+    for sign in [1f64, -1f64] {
+        let piece = simplify(clip(&corners, |p| side(p) * sign));
+        if piece.len() < 3 || polygon_area(&piece) < MIN_PIECE_AREA {
+            return None;
+        }
+        let (cx, cy) = centroid(&piece);
+        let (sin, cos) = (shape.rot as f64).to_radians().sin_cos();
+        let mut result = shape.clone();
+        result.held = false;
+        // Undo the rotation so `corners_at` puts the piece back where it was
+        result.vertices = piece.iter()
+            .map(|(px, py)| {
+                let (dx, dy) = (px - cx, py - cy);
+                (dx*cos - dy*sin, dx*sin + dy*cos)
+            })
+            .collect();
+        result.prev_x = cx + shape.prev_x - x;
+        result.prev_y = cy + shape.prev_y - y;
+        result.x = cx + shape.x - x;
+        result.y = cy + shape.y - y;
+        result.vel_x += nx * SLICE_SEPARATION * sign;
+        result.vel_y += ny * SLICE_SEPARATION * sign;
+        pieces.push(result);
+    }
+    Some(pieces)
+}
+
+// The part of a convex polygon where `distance` is at least 0, in the same
+// winding
+fn clip(points: &[(f64, f64)], distance: impl Fn((f64, f64)) -> f64) -> Vec<(f64, f64)> {
+    let mut result = vec![];
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let (da, db) = (distance(a), distance(b));
+        if da >= 0f64 {
+            result.push(a);
+        }
+        if da > 0f64 && db < 0f64 || da < 0f64 && db > 0f64 {
+            let t = da / (da - db);
+            result.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+        }
+    }
+    result
+}
+
+// Cutting a corner off adds a side, so the piece left of a shape with
+// `MAX_SIDES` sides loses the corners that stick out least until it has a
+// price again
+fn simplify(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    while points.len() > MAX_SIDES as usize {
+        let n = points.len();
+        let flattest = (0..n)
+            .map(|i| (i, polygon_area(&[points[(i + n - 1) % n], points[i], points[(i + 1) % n]])))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        points.remove(flattest);
+    }
+    points
+}
+
+fn centroid(points: &[(f64, f64)]) -> (f64, f64) {
+    let (mut cx, mut cy, mut twice_area) = (0f64, 0f64, 0f64);
+    for i in 0..points.len() {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % points.len()];
+        let cross = x1*y2 - x2*y1;
+        twice_area += cross;
+        cx += (x1 + x2) * cross;
+        cy += (y1 + y2) * cross;
+    }
+    (cx / (3f64 * twice_area), cy / (3f64 * twice_area))
+}
+
+fn normal(dx: f64, dy: f64) -> (f64, f64) {
+    let length = (dx * dx + dy * dy).sqrt();
+    (-dy / length, dx / length)
+}
+
+fn project(points: &[(f64, f64)], axis: (f64, f64)) -> (f64, f64) {
+    let mut min = f64::MAX;
+    let mut max = f64::MIN;
+    for (x, y) in points {
+        let d = x * axis.0 + y * axis.1;
+        min = min.min(d);
+        max = max.max(d);
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use raylib::prelude::Color;
+
+    use super::*;
+    use crate::game_logic::{prices::price_table, shape::{SHAPE_RADIUS, regular_shape}};
+
+    fn laser(y: f64, angle: f64) -> Laser {
+        Laser { min_x: 0f64, max_x: WORLD_WIDTH as f64, y, angle, thickness: 7f64 }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    // Slices a resting shape centered on the laser and checks what holds for
+    // every cut: the pieces add up to the shape, balance on its center and
+    // each lies on its own side of the beam. Returns the pieces' areas and
+    // centers.
+    fn slice_through_center(sides: i32, rot: f32, angle: f64) -> Vec<(f64, (f64, f64))> {
+        let (x, y) = (200f64, 300f64);
+        let laser = laser(y, angle);
+        let shape = Shape { rot, ..regular_shape(sides, Color::GRAY, x, y) };
+        let pieces = slice(&laser, &shape, x, y).expect("a cut through the center");
+        assert_eq!(pieces.len(), 2);
+
+        let ((x1, y1), (x2, y2)) = laser.endpoints();
+        let (nx, ny) = normal(x2 - x1, y2 - y1);
+        let mut result = vec![];
+        for (piece, sign) in pieces.iter().zip([1f64, -1f64]) {
+            let corners = piece.corners_at(piece.x, piece.y);
+            for (cx, cy) in &corners {
+                assert!(((cx - x) * nx + (cy - y) * ny) * sign >= -1e-9);
+            }
+            // Local vertices are centered on the piece and only rotated
+            let (local_x, local_y) = centroid(&piece.vertices);
+            assert!(close(local_x, 0f64) && close(local_y, 0f64));
+            assert!(close(polygon_area(&piece.vertices), polygon_area(&corners)));
+            let (world_x, world_y) = centroid(&corners);
+            assert!(close(world_x, piece.x) && close(world_y, piece.y));
+            result.push((polygon_area(&corners), (piece.x, piece.y)));
+        }
+
+        let total: f64 = result.iter().map(|(area, _)| area).sum();
+        assert!(close(total, polygon_area(&shape.vertices)));
+        let balance_x: f64 = result.iter().map(|(area, (px, _))| area * px).sum::<f64>() / total;
+        let balance_y: f64 = result.iter().map(|(area, (_, py))| area * py).sum::<f64>() / total;
+        assert!(close(balance_x, x) && close(balance_y, y));
+        result
+    }
+
+    #[test]
+    fn square_splits_into_two_triangles() {
+        // Unrotated squares stand on a corner, the cut goes corner to corner
+        let pieces = slice_through_center(4, 0f32, 0f64);
+        for (area, (px, py)) in &pieces {
+            assert!(close(*area, SHAPE_RADIUS * SHAPE_RADIUS));
+            assert!(close(*px, 200f64));
+            assert!(close((py - 300f64).abs(), SHAPE_RADIUS / 3f64));
+        }
+    }
+
+    #[test]
+    fn level_square_splits_into_two_rectangles() {
+        let side = SHAPE_RADIUS * 2f64.sqrt();
+        let pieces = slice_through_center(4, 45f32, 0f64);
+        for (area, (_, py)) in &pieces {
+            assert!(close(*area, side * side / 2f64));
+            assert!(close((py - 300f64).abs(), side / 4f64));
+        }
+    }
+
+    #[test]
+    fn triangle_loses_its_tip() {
+        // The tip points down, the cut through the center leaves a similar
+        // triangle 2/3 its size
+        let pieces = slice_through_center(3, 0f32, 0f64);
+        let total = 3f64 * 3f64.sqrt() / 4f64 * SHAPE_RADIUS * SHAPE_RADIUS;
+        let (tip_area, (_, tip_y)) = pieces[0];
+        assert!(close(tip_area, total * 4f64 / 9f64));
+        assert!(close(tip_y, 300f64 + SHAPE_RADIUS / 3f64));
+    }
+
+    #[test]
+    fn rotated_shapes_under_a_tilted_laser() {
+        for (sides, rot) in [(3, 17f32), (4, 33f32), (6, 71f32)] {
+            slice_through_center(sides, rot, 25f64);
+        }
+    }
+
+    // A square that was at `from` before the last step and is at `to` now
+    fn moving_square(from: (f64, f64), to: (f64, f64)) -> Shape {
+        Shape { prev_x: from.0, prev_y: from.1, ..regular_shape(4, Color::GRAY, to.0, to.1) }
+    }
+
+    #[test]
+    fn fast_shapes_dont_tunnel_through() {
+        let (level, tilted) = (laser(500f64, 0f64), laser(500f64, 30f64));
+        // Far more than the shape and beam are thick in one step, both ways
+        assert!(swept_hit(&level, &moving_square((200f64, 100f64), (200f64, 900f64))));
+        assert!(swept_hit(&level, &moving_square((200f64, 900f64), (200f64, 100f64))));
+        assert!(swept_hit(&level, &moving_square((100f64, 200f64), (500f64, 800f64))));
+        assert!(swept_hit(&tilted, &moving_square((320f64, 0f64), (320f64, 1000f64))));
+    }
+
+    #[test]
+    fn near_misses_dont_hit() {
+        let short = Laser { min_x: 300f64, max_x: 400f64, ..laser(500f64, 0f64) };
+        // Straight down beside the beam
+        assert!(!swept_hit(&short, &moving_square((200f64, 100f64), (200f64, 900f64))));
+        // Diagonally past its left end, the bounding boxes overlap
+        assert!(!swept_hit(&short, &moving_square((150f64, 560f64), (390f64, 320f64))));
+        // Along it, just above
+        assert!(!swept_hit(&short, &moving_square((250f64, 460f64), (450f64, 460f64))));
+        // Stopped short of it
+        assert!(!swept_hit(&short, &moving_square((350f64, 300f64), (350f64, 470f64))));
+        assert!(swept_hit(&short, &moving_square((350f64, 300f64), (350f64, 474f64))));
+    }
+
+    #[test]
+    fn slices_of_the_biggest_shapes_keep_a_price() {
+        // A tip above the beam and every other corner on a curve below it,
+        // the lower piece gets all of those and two more where the cut is
+        let mut vertices = vec![(0f64, -60f64)];
+        for i in 0..MAX_SIDES - 1 {
+            let t = 30f64 - 60f64 * i as f64 / (MAX_SIDES - 2) as f64;
+            vertices.push((t, 20f64 + 5f64 * (1f64 - (t / 30f64).powi(2))));
+        }
+        let shape = Shape { vertices, ..regular_shape(3, Color::GRAY, 200f64, 300f64) };
+        let pieces = slice(&laser(300f64, 0f64), &shape, 200f64, 300f64).expect("a cut through the middle");
+        assert_eq!(pieces.iter().map(|p| p.sides()).collect::<Vec<i32>>(), vec![MAX_SIDES, 3]);
+        for piece in &pieces {
+            assert!(price_table().base_price(piece).is_some());
+        }
+    }
+
+    #[test]
+    fn lasers_load_from_text() {
+        assert_eq!(load_lasers(""), DEFAULT_LASERS.to_vec());
+        assert_eq!(load_lasers(&lasers_text(&DEFAULT_LASERS)), DEFAULT_LASERS.to_vec());
+        let lasers = load_lasers("# two lasers\n1 0 640 400 0 7\n1 100 300 600 -20 5\n");
+        assert_eq!(lasers.len(), 2);
+        assert_eq!(lasers[1], (1, Laser { min_x: 100f64, max_x: 300f64, y: 600f64, angle: -20f64, thickness: 5f64 }));
+        assert_eq!(load_lasers(&lasers_text(&lasers)), lasers);
+    }
+
+    #[test]
+    fn bad_lasers_are_skipped() {
+        let lasers = load_lasers("0 0 640 400 0 7\n1 300 100 400 0 7\n1 0 640 400 90 7\n1 0 640 400 0 0\n1 0 640\n1 0 640 300 0 7\n");
+        assert_eq!(lasers, vec![(1, Laser { min_x: 0f64, max_x: 640f64, y: 300f64, angle: 0f64, thickness: 7f64 })]);
+    }
+
+    #[test]
+    fn lasers_without_an_upgrade_are_skipped() {
+        let lasers = load_lasers("2 0 640 300 0 7\n1 0 640 400 0 7\n99 0 640 500 0 7\n");
+        assert_eq!(lasers.iter().map(|(level, _)| *level).collect::<Vec<u64>>(), vec![1]);
+    }
+}
@@ -3,4 +3,20 @@ pub mod tick;
 pub mod time;
 pub mod pipe;
 pub mod shape;
-pub mod upgrades;
\ No newline at end of file
+pub mod upgrades;
+pub mod achievements;
+pub mod colors;
+pub mod events;
+pub mod action;
+pub mod world;
+pub mod laser;
+pub mod score;
+pub mod prices;
+pub mod multipliers;
+pub mod boosts;
+pub mod playfield;
+pub mod emission;
+pub mod physics;
+pub mod walls;
+#[cfg(feature = "dev")]
+pub mod commands;
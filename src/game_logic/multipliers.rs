@@ -0,0 +1,144 @@
+use raylib::prelude::Color;
+
+use super::{achievements::achievement_bonus_percent, state::{GameState, Shape}, upgrades::Upgrade};
+use super::boosts::{BoostKind, boost_factor};
+
+// Sale bonuses stack by category: bonuses in the same category add their
+// percentages, the categories multiply. Two "all sales x2" upgrades make x3,
+// adding "hexagon value x5" on top makes hexagons x15.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MultiplierCategory {
+    // Upgrades that raise every sale
+    AllSales,
+    Achievements,
+    // A running sale value boost
+    Boost,
+    Sides(i32),
+    Color(Color),
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Multiplier {
+    pub category: MultiplierCategory,
+    // Summed over everything in the category, 100 doubles the price
+    pub bonus_percent: u64,
+}
+
+// The bonus an upgrade adds and where it stacks
+pub fn upgrade_multiplier(upgrade: &Upgrade) -> Option<Multiplier> {
+    let (category, bonus_percent) = match upgrade {
+        Upgrade::SaleMultiplier(_) => (MultiplierCategory::AllSales, 100),
+        Upgrade::SidesMultiplier(sides) => (MultiplierCategory::Sides(*sides), 400),
+        Upgrade::ColorMultiplier(color) => (MultiplierCategory::Color(*color), 50),
+        _ => return None,
+    };
+    Some(Multiplier { category, bonus_percent })
+}
+
+// One entry per category with a bonus, in the order they were first earned
+pub fn active_multipliers(state: &GameState) -> Vec<Multiplier> {
+    let mut result: Vec<Multiplier> = vec![];
+    let achievements = Multiplier {
+        category: MultiplierCategory::Achievements,
+        bonus_percent: achievement_bonus_percent(state) as u64,
+    };
+    let boost = Multiplier {
+        category: MultiplierCategory::Boost,
+        bonus_percent: (boost_factor(state, BoostKind::SaleValue) - 1) * 100,
+    };
+    for multiplier in state.upgrades.iter().filter_map(upgrade_multiplier).chain([achievements, boost]) {
+        if multiplier.bonus_percent == 0 {
+            continue;
+        }
+        match result.iter_mut().find(|m| m.category == multiplier.category) {
+            Some(existing) => existing.bonus_percent += multiplier.bonus_percent,
+            None => result.push(multiplier),
+        }
+    }
+    result
+}
+
+impl Multiplier {
+    pub fn applies_to(&self, shape: &Shape) -> bool {
+        match self.category {
+            MultiplierCategory::AllSales | MultiplierCategory::Achievements | MultiplierCategory::Boost => true,
+            MultiplierCategory::Sides(sides) => shape.sides() == sides,
+            MultiplierCategory::Color(color) => shape.color == color,
+        }
+    }
+}
+
+// The combined multiplier for a shape as a fraction, kept whole so prices stay
+// exact
+pub fn sale_multiplier(multipliers: &[Multiplier], shape: &Shape) -> (u64, u64) {
+    let mut numerator = 1u64;
+    let mut denominator = 1u64;
+    for multiplier in multipliers.iter().filter(|m| m.applies_to(shape)) {
+        numerator = numerator.saturating_mul(100 + multiplier.bonus_percent);
+        denominator = denominator.saturating_mul(100);
+    }
+    (numerator, denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{achievements::Achievement, boosts::Boost, shape::regular_shape, state::seeded_state};
+
+    fn shape(sides: i32, color: Color) -> Shape {
+        regular_shape(sides, color, 0f64, 0f64)
+    }
+
+    #[test]
+    fn nothing_earned_leaves_prices_alone() {
+        let mut state = seeded_state(0);
+        // Worth no bonus, so no category either
+        state.achievements = vec![Achievement::FirstSale];
+        assert_eq!(active_multipliers(&state), vec![]);
+        assert_eq!(sale_multiplier(&[], &shape(3, Color::GRAY)), (1, 1));
+    }
+
+    #[test]
+    fn bonuses_in_a_category_add_up() {
+        let mut state = seeded_state(0);
+        state.upgrades = vec![Upgrade::SaleMultiplier(1), Upgrade::SaleMultiplier(2)];
+        let multipliers = active_multipliers(&state);
+        assert_eq!(multipliers, vec![Multiplier { category: MultiplierCategory::AllSales, bonus_percent: 200 }]);
+        // x2 and x2 make x3, not x4
+        assert_eq!(sale_multiplier(&multipliers, &shape(6, Color::GRAY)), (300, 100));
+        assert_eq!(sale_multiplier(&multipliers, &shape(4, Color::PURPLE)), (300, 100));
+    }
+
+    #[test]
+    fn categories_multiply() {
+        let mut state = seeded_state(0);
+        state.upgrades = vec![Upgrade::SaleMultiplier(1), Upgrade::SidesMultiplier(6), Upgrade::SaleMultiplier(2)];
+        let multipliers = active_multipliers(&state);
+        assert_eq!(multipliers, vec![
+            Multiplier { category: MultiplierCategory::AllSales, bonus_percent: 200 },
+            Multiplier { category: MultiplierCategory::Sides(6), bonus_percent: 400 },
+        ]);
+        // x3 from the sale upgrades times x5 for hexagons
+        assert_eq!(sale_multiplier(&multipliers, &shape(6, Color::GRAY)), (300 * 500, 100 * 100));
+        assert_eq!(sale_multiplier(&multipliers, &shape(5, Color::GRAY)), (300, 100));
+    }
+
+    #[test]
+    fn achievements_and_boosts_are_their_own_categories() {
+        let mut state = seeded_state(0);
+        state.upgrades = vec![Upgrade::SaleMultiplier(1), Upgrade::ColorMultiplier(Color::RED)];
+        state.achievements = vec![Achievement::FirstSale, Achievement::FirstHexagonSold, Achievement::AllColorsOnPipe];
+        state.boosts = vec![Boost { kind: BoostKind::SaleValue, remaining: 10_000 }];
+        let multipliers = active_multipliers(&state);
+        assert_eq!(multipliers, vec![
+            Multiplier { category: MultiplierCategory::AllSales, bonus_percent: 100 },
+            Multiplier { category: MultiplierCategory::Color(Color::RED), bonus_percent: 50 },
+            // 2% and 3% from the achievements, a running boost is x7
+            Multiplier { category: MultiplierCategory::Achievements, bonus_percent: 5 },
+            Multiplier { category: MultiplierCategory::Boost, bonus_percent: 600 },
+        ]);
+        // x2 * x1.05 * x7 = x14.7, and x1.5 more for red
+        assert_eq!(sale_multiplier(&multipliers, &shape(3, Color::GRAY)), (200 * 105 * 700, 100 * 100 * 100));
+        assert_eq!(sale_multiplier(&multipliers, &shape(3, Color::RED)), (200 * 150 * 105 * 700, 100 * 100 * 100 * 100));
+    }
+}
@@ -0,0 +1,171 @@
+use super::{state::GameState, upgrades::Upgrade};
+
+// How shapes move, in world units and seconds. The defaults can be changed
+// from assets/physics.txt, upgrades change them further during a game.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Physics {
+    pub gravity: f64,
+    // Fraction of the sideways speed lost per second
+    pub drag: f64,
+    // Fastest a shape falls
+    pub terminal_velocity: f64,
+    // Downwards speed of a shape leaving a pipe
+    pub drop_speed: f64,
+    // Rotation speed of new shapes in degrees per second, picked between the two
+    pub spin_min: f32,
+    pub spin_max: f32,
+    // New shapes start up to this far left or right of the pipe's drop point
+    pub spawn_spread: f64,
+    // and get pushed sideways up to this fast
+    pub spawn_push: f64,
+}
+
+pub const DEFAULT_PHYSICS: Physics = Physics {
+    gravity: 90f64,
+    drag: 4f64,
+    terminal_velocity: 1000f64,
+    drop_speed: 300f64,
+    spin_min: 0.1,
+    spin_max: 0.1,
+    spawn_spread: 10f64,
+    spawn_push: 50f64,
+};
+
+// Each anti-gravity level takes this share off gravity and the drop speed
+const ANTI_GRAVITY_PERCENT: u64 = 25;
+pub const ANTI_GRAVITY_LEVELS: u64 = 3;
+
+// The physics for the current state of the game
+pub fn physics(state: &GameState) -> Physics {
+    let mut physics = state.physics;
+    let anti_gravity = state.upgrades.iter()
+        .filter_map(|u| match u {
+            Upgrade::AntiGravity(level) => Some(*level),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        .min(ANTI_GRAVITY_LEVELS);
+    let factor = 1f64 - (anti_gravity * ANTI_GRAVITY_PERCENT) as f64 / 100f64;
+    physics.gravity *= factor;
+    physics.drop_speed *= factor;
+    physics
+}
+
+// The defaults with the `key = value` lines in `contents` applied, see
+// assets/physics.txt. Bad lines are reported and skipped like the upgrade
+// overrides.
+pub fn load_physics(contents: &str) -> Physics {
+    let mut physics = DEFAULT_PHYSICS;
+    for line in contents.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        if let Err(e) = apply_setting(&mut physics, line) {
+            eprintln!("Ignoring physics setting '{}': {}", line, e);
+        }
+    }
+    if physics.spin_min > physics.spin_max {
+        eprintln!("Physics spin_min is above spin_max, using spin_max for both");
+        physics.spin_min = physics.spin_max;
+    }
+    physics
+}
+
+// The lines `load_physics` reads back into the same physics
+pub fn physics_text(physics: &Physics) -> String {
+    format!(
+        "gravity = {}\ndrag = {}\nterminal_velocity = {}\ndrop_speed = {}\nspin_min = {}\nspin_max = {}\nspawn_spread = {}\nspawn_push = {}\n",
+        physics.gravity,
+        physics.drag,
+        physics.terminal_velocity,
+        physics.drop_speed,
+        physics.spin_min,
+        physics.spin_max,
+        physics.spawn_spread,
+        physics.spawn_push,
+    )
+}
+
+fn apply_setting(physics: &mut Physics, line: &str) -> Result<(), String> {
+    let (key, value) = line.split_once('=').ok_or("expected key = value")?;
+    let value: f64 = value.trim().parse().map_err(|_| format!("bad number '{}'", value.trim()))?;
+    if !value.is_finite() || value < 0f64 {
+        return Err("expected zero or a positive number".to_string());
+    }
+    // Without gravity or a drop speed new shapes would hang under the pipes
+    let still = "shapes would never leave the pipes".to_string();
+    match key.trim() {
+        "gravity" if value == 0f64 && physics.drop_speed == 0f64 => return Err(still),
+        "gravity" => physics.gravity = value,
+        "drag" => physics.drag = value,
+        "terminal_velocity" if value == 0f64 => return Err("shapes would never fall".to_string()),
+        "terminal_velocity" => physics.terminal_velocity = value,
+        "drop_speed" if value == 0f64 && physics.gravity == 0f64 => return Err(still),
+        "drop_speed" => physics.drop_speed = value,
+        "spin_min" => physics.spin_min = value as f32,
+        "spin_max" => physics.spin_max = value as f32,
+        "spawn_spread" => physics.spawn_spread = value,
+        "spawn_push" => physics.spawn_push = value,
+        key => return Err(format!("unknown key '{}'", key)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::state::seeded_state;
+
+    const CUSTOM: Physics = Physics {
+        gravity: 45.5,
+        drag: 0f64,
+        terminal_velocity: 800f64,
+        drop_speed: 0f64,
+        spin_min: 5f32,
+        spin_max: 12.5,
+        spawn_spread: 0f64,
+        spawn_push: 70f64,
+    };
+
+    #[test]
+    fn physics_round_trip() {
+        assert_eq!(load_physics(""), DEFAULT_PHYSICS);
+        assert_eq!(load_physics(&physics_text(&DEFAULT_PHYSICS)), DEFAULT_PHYSICS);
+        assert_eq!(load_physics(&physics_text(&CUSTOM)), CUSTOM);
+        // Only the keys given change, comments and spaces are fine
+        let physics = load_physics("# lighter\n  gravity=45.5  \n\nspin_max = 12.5\n");
+        assert_eq!(physics, Physics { gravity: 45.5, spin_max: 12.5, ..DEFAULT_PHYSICS });
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        let physics = load_physics("gravity 20\ndrag = fast\ndrag = -1\ndrag = inf\nwind = 3\nterminal_velocity = 0\nspawn_push = 60\n");
+        assert_eq!(physics, Physics { spawn_push: 60f64, ..DEFAULT_PHYSICS });
+    }
+
+    #[test]
+    fn shapes_always_leave_the_pipes() {
+        // Either one on its own is fine, the second line of each pair is not
+        let physics = load_physics("gravity = 0\ndrop_speed = 0\n");
+        assert_eq!(physics, Physics { gravity: 0f64, ..DEFAULT_PHYSICS });
+        let physics = load_physics("drop_speed = 0\ngravity = 0\n");
+        assert_eq!(physics, Physics { drop_speed: 0f64, ..DEFAULT_PHYSICS });
+    }
+
+    #[test]
+    fn spin_min_above_spin_max_is_clamped() {
+        let physics = load_physics("spin_min = 30\nspin_max = 20\n");
+        assert_eq!((physics.spin_min, physics.spin_max), (20f32, 20f32));
+    }
+
+    #[test]
+    fn anti_gravity_slows_falling_and_dropping() {
+        let mut state = seeded_state(0);
+        assert_eq!(physics(&state), state.physics);
+        state.upgrades = vec![Upgrade::AntiGravity(2), Upgrade::AntiGravity(1)];
+        let physics_now = physics(&state);
+        assert_eq!(physics_now, Physics { gravity: 45f64, drop_speed: 150f64, ..state.physics });
+        // Levels past the last one take no more off
+        state.upgrades.push(Upgrade::AntiGravity(ANTI_GRAVITY_LEVELS + 2));
+        let physics_now = physics(&state);
+        assert_eq!((physics_now.gravity, physics_now.drop_speed), (22.5, 75f64));
+    }
+}
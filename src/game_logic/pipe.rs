@@ -1,15 +1,44 @@
-use std::f32::consts::{E, PI};
+use std::f32::consts::E;
 
-use rand::{Rng};
-use raylib::{prelude::Color, ffi::log2l};
+use rand::{Rng, rngs::StdRng};
+use raylib::prelude::Color;
 
-use super::{state::{GameState, GamePipe, Shape}};
+use super::{state::{GameState, GamePipe, Shape}, events::GameEvent, shape::regular_shape};
 use super::upgrades::Upgrade;
+use super::boosts::{BoostKind, boost_factor};
+use super::emission::{drop_colors, sync_emission};
+use super::physics::{Physics, physics};
+
+// Size of the nozzle at the left end of the pipe sprite, the part that can be
+// clicked. The long horizontal arm is hidden behind the other pipes.
+const NOZZLE_WIDTH: f64 = 70f64;
+const NOZZLE_HEIGHT: f64 = 197f64;
+
+// Top left corner of the pipe sprite in world coordinates
+pub fn pipe_position(i: usize) -> (f64, f64) {
+    ((30 + 120*i) as f64, (30 + 10*i) as f64)
+}
+
+// Where the pipe's shapes come out
+pub fn drop_point(i: usize) -> (f64, f64) {
+    let (x, y) = pipe_position(i);
+    (x + 33f64, y + 180f64)
+}
+
+// The pipe whose nozzle is at the world position. Pipes are drawn back to
+// front, so the first one found is the one on top.
+pub fn pipe_at(state: &GameState, x: f64, y: f64) -> Option<usize> {
+    (0..state.pipes.len()).find(|i| {
+        let (left, top) = pipe_position(*i);
+        x >= left && x < left + NOZZLE_WIDTH && y >= top && y < top + NOZZLE_HEIGHT
+    })
+}
 
 pub fn pipe_tick(game_state: &mut GameState, dt: u64) {
     pipe_speed_tick(game_state);
     pipe_shape_drop_tick(game_state, dt);
     pipe_count_tick(game_state);
+    sync_emission(game_state);
 }
 
 fn pipe_count_tick(game_state: &mut GameState) {
@@ -20,6 +49,7 @@ fn pipe_count_tick(game_state: &mut GameState) {
             speed: 1, 
             last_drop: 0,
         });
+        game_state.events.push(GameEvent::PipeUnlocked(game_state.pipes.len() - 1));
     }
     if game_state.pipes.len() < 3 && game_state.upgrades.contains(&Upgrade::Pipe3) {
         game_state.pipes.push(GamePipe { 
@@ -28,6 +58,7 @@ fn pipe_count_tick(game_state: &mut GameState) {
             speed: 1, 
             last_drop: 0,
         });
+        game_state.events.push(GameEvent::PipeUnlocked(game_state.pipes.len() - 1));
     }
     if game_state.pipes.len() < 4 && game_state.upgrades.contains(&Upgrade::Pipe4) {
         game_state.pipes.push(GamePipe { 
@@ -36,6 +67,7 @@ fn pipe_count_tick(game_state: &mut GameState) {
             speed: 1, 
             last_drop: 0,
         });
+        game_state.events.push(GameEvent::PipeUnlocked(game_state.pipes.len() - 1));
     }
 }
 
@@ -43,44 +75,46 @@ fn pipe_speed_tick(game_state: &mut GameState) {
     game_state.pipes[0].speed = game_state.upgrades.iter().fold(1u64, |p, upg| {
         if let Upgrade::Pipe1Speed(speed) = upg {
             if speed > &p {
-                return speed.clone();
+                return *speed;
             }
         }
-        return p;
+        p
     });
     if game_state.pipes.len() >= 2 {
         game_state.pipes[1].speed = game_state.upgrades.iter().fold(1u64, |p, upg| {
             if let Upgrade::Pipe2Speed(speed) = upg {
                 if speed > &p {
-                    return speed.clone();
+                    return *speed;
                 }
             }
-            return p;
+            p
         });
     }
     if game_state.pipes.len() >= 3 {
         game_state.pipes[2].speed = game_state.upgrades.iter().fold(1u64, |p, upg| {
             if let Upgrade::Pipe3Speed(speed) = upg {
                 if speed > &p {
-                    return speed.clone();
+                    return *speed;
                 }
             }
-            return p;
+            p
         });
     }
     if game_state.pipes.len() >= 4 {
         game_state.pipes[3].speed = game_state.upgrades.iter().fold(1u64, |p, upg| {
             if let Upgrade::Pipe4Speed(speed) = upg {
                 if speed > &p {
-                    return speed.clone();
+                    return *speed;
                 }
             }
-            return p;
+            p
         });
     }
 }
 
 fn pipe_shape_drop_tick(game_state: &mut GameState, dt: u64) {
+    // A drop speed boost runs the pipes' clocks faster
+    let dt = dt * boost_factor(game_state, BoostKind::DropSpeed);
     for i in 0..game_state.pipes.len() {
         let mut drop_ct = 0;
         {
@@ -105,60 +139,107 @@ fn sigmoid(x: f64) -> f64 {
     1f64 / (1f64 + E.powf(-x as f32) as f64)
 }
 
-fn drop_delay(pipe: &GamePipe) -> u64 {
-    let per_sec;
-    unsafe {
-        per_sec = log2l(pipe.speed as f64)/log2l(1.2)*0.05*pipe.speed as f64 + 0.2;
-    }
+// Milliseconds between drops, at least 1 however fast the pipe gets
+pub fn drop_delay(pipe: &GamePipe) -> u64 {
+    let per_sec = (pipe.speed as f64).log2()/1.2f64.log2()*0.05*pipe.speed as f64 + 0.2;
     let delay = 1000f64/per_sec;
-    delay as u64
+    (delay as u64).max(1)
 }
 
-fn drop_shapes(state: &mut GameState, i: i32, drop_ct: u32) {
-    let unlocked_colors: Vec<Color> = unlocked_colors(state, i);
-    let pipe = &state.pipes[i as usize];
+pub fn drop_shapes(state: &mut GameState, i: i32, drop_ct: u32) {
+    sync_emission(state);
+    let shapes_per_drop = unlocked_colors(state, i).len() as u64;
+    let physics = physics(state);
+    state.events.push(GameEvent::ShapesDropped { sides: state.pipes[i as usize].sides });
     for _ in 0..drop_ct {
-        for color in &unlocked_colors {
-            let pressure = (pipe.speed*unlocked_colors.len() as u64) as f64 * 0.03;
-            let x_factor = rand::thread_rng().gen_range(-1f64..1f64);
-            let x_offset_vel = (sigmoid(x_factor*5f64)*2f64 - 1f64) * 50f64;
-            let x = 30 + 120*i + 33;
-            let y = 30 + 10*i + 180;
+        let colors = drop_colors(state, i as usize, shapes_per_drop);
+        let pipe = &state.pipes[i as usize];
+        for color in &colors {
+            let pressure = (pipe.speed*shapes_per_drop) as f64 * 0.03;
+            let x_factor = state.rng.gen_range(-1f64..1f64);
+            let x_offset_vel = (sigmoid(x_factor*5f64)*2f64 - 1f64) * physics.spawn_push;
+            let (x, y) = drop_point(i as usize);
+            let x = x + state.rng.gen_range(-1f64..1f64)*physics.spawn_spread;
             state.shapes.push(Shape { 
-                x: x as f64 + rand::thread_rng().gen_range(-1f64..1f64)*10f64, 
-                y: y as f64, 
                 vel_x: x_offset_vel * pressure, 
-                vel_y: 300f64, 
-                rot: rand::thread_rng().gen_range(0f32..PI*360f32), 
-                rot_vel: 0.1f32, 
-                sides: pipe.sides, 
-                color: color.clone(),
-                laser_cuts: 0
+                vel_y: physics.drop_speed, 
+                rot: state.rng.gen_range(0f32..360f32), 
+                rot_vel: spin(&mut state.rng, &physics), 
+                ..regular_shape(pipe.sides, *color, x, y)
             });
         }
     }
 }
 
-fn unlocked_colors(state: &mut GameState, i: i32) -> Vec<Color> {
-    let mut result = vec![Color::GRAY];
+// Skips the RNG for a fixed spin, so the default physics drop the same
+// shapes as before spin was configurable
+pub fn spin(rng: &mut StdRng, physics: &Physics) -> f32 {
+    if physics.spin_min >= physics.spin_max {
+        return physics.spin_min;
+    }
+    rng.gen_range(physics.spin_min..physics.spin_max)
+}
+
+pub fn unlocked_colors(state: &GameState, i: i32) -> Vec<Color> {
+    let mut result = vec![state.pipes[i as usize].color];
     for upgrade in &state.upgrades {
         if i == 0 {
             if let Upgrade::Pipe1Color(c) = upgrade {
-                result.push(c.clone());
+                result.push(*c);
             }
         } else if i == 1 {
             if let Upgrade::Pipe2Color(c) = upgrade {
-                result.push(c.clone());
+                result.push(*c);
             }
         } else if i == 2 {
             if let Upgrade::Pipe3Color(c) = upgrade {
-                result.push(c.clone());
+                result.push(*c);
             }
         } else if i == 3 { 
             if let Upgrade::Pipe4Color(c) = upgrade {
-                result.push(c.clone());
+                result.push(*c);
             }
         }
     }
-    return result;
-}
\ No newline at end of file
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{state::seeded_state, tick::{STEP, tick}, upgrades::unlock_upgrades::SPEED_LEVELS};
+
+    fn pipe(speed: u64) -> GamePipe {
+        GamePipe { sides: 3, color: Color::GRAY, speed, last_drop: 0 }
+    }
+
+    #[test]
+    fn drop_delay_falls_as_speed_rises() {
+        assert_eq!(drop_delay(&pipe(1)), 5000);
+        assert!(drop_delay(&pipe(2)) < drop_delay(&pipe(1)));
+        assert!(drop_delay(&pipe(100)) < drop_delay(&pipe(10)));
+    }
+
+    #[test]
+    fn catalog_pipes_drop_at_most_once_per_step() {
+        assert!(drop_delay(&pipe(SPEED_LEVELS)) >= STEP);
+    }
+
+    #[test]
+    fn drop_delay_is_never_zero() {
+        for speed in [580, 1_000, 100_000, u64::MAX] {
+            assert_eq!(drop_delay(&pipe(speed)), 1, "speed {}", speed);
+        }
+    }
+
+    #[test]
+    fn fastest_pipe_drops_every_millisecond() {
+        let mut state = seeded_state(1);
+        state.upgrades.push(Upgrade::Pipe1Speed(100_000));
+        for _ in 0..3 {
+            tick(&mut state, STEP);
+        }
+        assert_eq!(state.pipes[0].speed, 100_000);
+        assert!(state.shapes.len() >= 2 * STEP as usize);
+    }
+}
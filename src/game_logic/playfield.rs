@@ -0,0 +1,114 @@
+use raylib::prelude::Color;
+
+use super::{boosts::catch_golden, multipliers::active_multipliers, pipe::{drop_shapes, pipe_at}, score::Score, state::GameState};
+use super::{prices::{price_table, regular_area}, shape::polygon_area};
+
+// How long a clicked shape's stats stay on screen, in milliseconds
+const INSPECTION_DURATION: u64 = 3000;
+// A press and release closer than this in world units is a click, not a drag
+const CLICK_SLOP: f64 = 8f64;
+// Seconds a held shape takes to close most of the gap to the mouse. Following
+// instead of jumping keeps the sweep smooth for the lasers and gives the shape
+// the mouse's speed when let go.
+pub const DRAG_FOLLOW: f64 = 0.05;
+
+// A shape being dragged with the mouse, the held shape has `Shape::held` set
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Drag {
+    pub start: (f64, f64),
+    pub target: (f64, f64),
+}
+
+// What a clicked shape was worth when it was clicked
+#[derive(PartialEq, Clone, Debug)]
+pub struct ShapeInspection {
+    pub sides: i32,
+    pub color: Color,
+    pub price: Score,
+    // Share of a full shape's area left after slicing
+    pub area_percent: u64,
+    pub laser_cuts: u32,
+    pub x: f64,
+    pub y: f64,
+    pub remaining: u64,
+}
+
+pub fn playfield_tick(state: &mut GameState, dt: u64) {
+    if let Some(inspection) = &mut state.inspection {
+        inspection.remaining = inspection.remaining.saturating_sub(dt);
+        if inspection.remaining == 0 {
+            state.inspection = None;
+        }
+    }
+    // The held shape may have been sold or sliced apart
+    if state.drag.is_some() && !state.shapes.iter().any(|s| s.held) {
+        state.drag = None;
+    }
+}
+
+// Mouse button released. Ends a drag, or handles a click on whatever is on top:
+// golden shapes, then pipes, then the other shapes. Clicking a pipe drops an
+// extra round of shapes and opens its panel.
+pub fn click(state: &mut GameState, x: f64, y: f64) {
+    if let Some(drag) = release(state) {
+        let (start_x, start_y) = drag.start;
+        if (x - start_x).hypot(y - start_y) < CLICK_SLOP {
+            inspect(state, x, y);
+        }
+        return;
+    }
+    if catch_golden(state, x, y) {
+        return;
+    }
+    if let Some(pipe) = pipe_at(state, x, y) {
+        drop_shapes(state, pipe as i32, 1);
+        state.pipe_panel = Some(pipe);
+        return;
+    }
+    inspect(state, x, y);
+}
+
+// Mouse button pressed, picks up the shape under it. Pipes are drawn over the
+// shapes, so nothing is picked up through them.
+pub fn grab(state: &mut GameState, x: f64, y: f64) {
+    release(state);
+    if pipe_at(state, x, y).is_some() {
+        return;
+    }
+    if let Some(shape) = state.shapes.iter_mut().rev().find(|s| !s.golden && s.contains(x, y)) {
+        shape.held = true;
+        state.drag = Some(Drag { start: (x, y), target: (x, y) });
+    }
+}
+
+pub fn drag(state: &mut GameState, x: f64, y: f64) {
+    if let Some(drag) = &mut state.drag {
+        drag.target = (x, y);
+    }
+}
+
+// Lets go of the held shape, it keeps the speed it was dragged at
+pub fn release(state: &mut GameState) -> Option<Drag> {
+    for shape in &mut state.shapes {
+        shape.held = false;
+    }
+    state.drag.take()
+}
+
+fn inspect(state: &mut GameState, x: f64, y: f64) {
+    let shape = match state.shapes.iter().rev().find(|s| !s.golden && s.contains(x, y)) {
+        Some(shape) => shape,
+        None => return,
+    };
+    let price = price_table().sale_price(shape, &active_multipliers(state)).unwrap_or(Score::zero());
+    state.inspection = Some(ShapeInspection {
+        sides: shape.sides(),
+        color: shape.color,
+        price,
+        area_percent: (polygon_area(&shape.vertices) / regular_area(shape.sides()) * 100f64).round() as u64,
+        laser_cuts: shape.laser_cuts.count_ones(),
+        x: shape.x,
+        y: shape.y,
+        remaining: INSPECTION_DURATION,
+    });
+}
@@ -0,0 +1,150 @@
+use num::{BigInt, FromPrimitive};
+use once_cell::sync::Lazy;
+use raylib::prelude::Color;
+
+use super::{colors::color_name, multipliers::{Multiplier, sale_multiplier}, score::Score, shape::{SHAPE_RADIUS, polygon_area}, state::Shape};
+
+// Most sides a shape can end up with, lasers that add a side stop here and
+// sliced pieces are trimmed back to it
+pub const MAX_SIDES: i32 = 16;
+
+// What a sold shape earns:
+//
+//   side price * color multiplier * share of a full shape's area * bonuses
+//
+// Only the bonuses change during a game, see `multipliers`.
+pub struct PriceTable {
+    // A full sized shape by side count, starting with triangles
+    side_prices: Vec<Score>,
+    color_multipliers: Vec<(Color, u64)>,
+}
+
+static PRICE_TABLE: Lazy<PriceTable> = Lazy::new(default_price_table);
+
+pub fn price_table() -> &'static PriceTable {
+    &PRICE_TABLE
+}
+
+fn default_price_table() -> PriceTable {
+    let side_prices = (3..=MAX_SIDES)
+        .map(|sides| {
+            let price = BigInt::from_u64(500).unwrap().pow(sides as u32 - 2) - 470;
+            Score::from_bigint(&price).unwrap()
+        })
+        .collect();
+    PriceTable {
+        side_prices,
+        color_multipliers: vec![
+            (Color::GRAY, 1),
+            (Color::RED, 10),
+            (Color::GREEN, 100),
+            (Color::BLUE, 1000),
+            (Color::YELLOW, 10_000),
+            (Color::PURPLE, 100_000),
+        ],
+    }
+}
+
+impl PriceTable {
+    // None for fewer than 3 or more than `MAX_SIDES` sides
+    pub fn side_price(&self, sides: i32) -> Option<&Score> {
+        if sides < 3 {
+            return None;
+        }
+        self.side_prices.get((sides - 3) as usize)
+    }
+
+    pub fn color_multiplier(&self, color: Color) -> Option<u64> {
+        self.color_multipliers.iter().find(|(c, _)| *c == color).map(|(_, m)| *m)
+    }
+
+    // For rejecting colors that would sell for nothing before they get into
+    // the game
+    pub fn check_color(&self, color: Color) -> Result<(), String> {
+        match self.color_multiplier(color) {
+            Some(_) => Ok(()),
+            None => Err(format!("{} shapes have no price", color_name(color).unwrap_or("Unknown"))),
+        }
+    }
+
+    // Before bonuses. Slices pay the part of a full shape's area they cover.
+    pub fn base_price(&self, shape: &Shape) -> Option<Score> {
+        let sides = shape.sides();
+        let price = self.side_price(sides)?.mul_u64(self.color_multiplier(shape.color)?);
+        let area_permille = (polygon_area(&shape.vertices) / regular_area(sides) * 1000f64).round() as u64;
+        if area_permille == 1000 {
+            return Some(price);
+        }
+        Some(price.mul_u64(area_permille).div_u64(1000))
+    }
+
+    // What the shape earns when sold with `multipliers` active
+    pub fn sale_price(&self, shape: &Shape, multipliers: &[Multiplier]) -> Option<Score> {
+        let (numerator, denominator) = sale_multiplier(multipliers, shape);
+        Some(self.base_price(shape)?.mul_u64(numerator).div_u64(denominator))
+    }
+}
+
+// Area of a regular polygon with corners `SHAPE_RADIUS` from its center
+pub fn regular_area(sides: i32) -> f64 {
+    let sides = sides as f64;
+    sides / 2f64 * SHAPE_RADIUS * SHAPE_RADIUS * (2f64 * std::f64::consts::PI / sides).sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{multipliers::active_multipliers, shape::regular_shape, state::seeded_state};
+
+    #[test]
+    fn every_side_count_has_a_positive_price() {
+        let table = price_table();
+        assert_eq!(table.side_price(3), Some(&Score::from(30u128)));
+        assert_eq!(table.side_price(4), Some(&Score::from(249_530u128)));
+        for sides in 3..MAX_SIDES {
+            assert!(table.side_price(sides).unwrap() > &Score::zero());
+            assert!(table.side_price(sides).unwrap() < table.side_price(sides + 1).unwrap());
+        }
+        // The formula goes negative below triangles, where the table stops,
+        // and a negative price can't be made into a `Score` at all
+        assert_eq!(Score::from_bigint(&(BigInt::from(1) - 470)), None);
+    }
+
+    #[test]
+    fn shapes_outside_the_table_have_no_price() {
+        let table = price_table();
+        let multipliers = active_multipliers(&seeded_state(0));
+        for sides in [-1, 0, 2, MAX_SIDES + 1] {
+            assert_eq!(table.side_price(sides), None);
+        }
+        let line = Shape { vertices: vec![(-10f64, 0f64), (10f64, 0f64)], ..regular_shape(3, Color::GRAY, 0f64, 0f64) };
+        assert_eq!(table.sale_price(&line, &multipliers), None);
+        let too_many = regular_shape(MAX_SIDES + 1, Color::GRAY, 0f64, 0f64);
+        assert_eq!(table.sale_price(&too_many, &multipliers), None);
+        assert!(table.sale_price(&regular_shape(MAX_SIDES, Color::GRAY, 0f64, 0f64), &multipliers).is_some());
+    }
+
+    #[test]
+    fn unknown_colors_have_no_price() {
+        let table = price_table();
+        let multipliers = active_multipliers(&seeded_state(0));
+        let orange = Color::new(255, 128, 0, 255);
+        assert_eq!(table.color_multiplier(orange), None);
+        assert!(table.check_color(orange).is_err());
+        assert_eq!(table.sale_price(&regular_shape(3, orange, 0f64, 0f64), &multipliers), None);
+        assert_eq!(table.color_multiplier(Color::BLUE), Some(1000));
+        assert!(table.check_color(Color::BLUE).is_ok());
+        assert_eq!(table.base_price(&regular_shape(3, Color::BLUE, 0f64, 0f64)), Some(Score::from(30_000u128)));
+    }
+
+    #[test]
+    fn slices_pay_their_share_of_the_area() {
+        let square = regular_shape(4, Color::RED, 0f64, 0f64);
+        // Half a square cut corner to corner sells as a triangle, by how much
+        // of a full triangle it covers
+        let half = Shape { vertices: square.vertices[..3].to_vec(), ..square.clone() };
+        let share = polygon_area(&half.vertices) / regular_area(3);
+        let expected = (30f64 * 10f64 * (share * 1000f64).round() / 1000f64).floor() as u128;
+        assert_eq!(price_table().base_price(&half), Some(Score::from(expected)));
+    }
+}
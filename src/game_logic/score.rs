@@ -0,0 +1,372 @@
+use std::{cmp::Ordering, fmt, ops::{AddAssign, SubAssign}, str::FromStr};
+
+use num::{BigInt, Signed, ToPrimitive};
+
+// A non-negative whole number for scores and sale prices that never
+// allocates. Exact in a `u128` up to about 3.4e38. Past that it keeps the top
+// 128 bits and a power of two, so it rounds down to 38 significant digits.
+// A sale smaller than that share of the score is lost. Converting to a
+// `BigInt` is exact, so a saved score loads back unchanged.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Score(Repr);
+
+// `Big` only ever holds numbers too large for `Small` and its mantissa always
+// has the top bit set, so every number has one form. That is what makes the
+// derived ordering and equality right, `Big` compares by exponent first.
+// Every result goes through `normalize`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Repr {
+    Small(u128),
+    // `mantissa << exponent`
+    Big { exponent: u32, mantissa: u128 },
+}
+
+impl Score {
+    pub fn zero() -> Score {
+        Score(Repr::Small(0))
+    }
+
+    // None for negative numbers. Rounds down past `u128`.
+    pub fn from_bigint(n: &BigInt) -> Option<Score> {
+        if n.is_negative() {
+            return None;
+        }
+        let shift = n.bits().saturating_sub(128);
+        let mantissa = (n >> shift).to_u128().unwrap();
+        Some(normalize(shift as u32, 0, mantissa))
+    }
+
+    pub fn to_bigint(&self) -> BigInt {
+        match self.0 {
+            Repr::Small(n) => BigInt::from(n),
+            Repr::Big { exponent, mantissa } => BigInt::from(mantissa) << exponent,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self.0 {
+            Repr::Small(n) => n as f64,
+            Repr::Big { exponent, mantissa } if exponent < 1024 => (mantissa as f64 * 2f64.powi(exponent as i32)).min(f64::MAX),
+            Repr::Big { .. } => f64::MAX,
+        }
+    }
+
+    pub fn mul_u64(&self, factor: u64) -> Score {
+        if let Repr::Small(n) = self.0 {
+            if let Some(product) = n.checked_mul(factor as u128) {
+                return Score(Repr::Small(product));
+            }
+        }
+        let (exponent, mantissa) = self.parts();
+        let factor = factor as u128;
+        // Two 64 bit halves of the mantissa times the factor, added up in 256 bits
+        let low = (mantissa as u64 as u128) * factor;
+        let high = (mantissa >> 64) * factor;
+        let (lo, carry) = low.overflowing_add(high << 64);
+        normalize(exponent, (high >> 64) + carry as u128, lo)
+    }
+
+    // Rounds down
+    pub fn div_u64(&self, divisor: u64) -> Score {
+        let (exponent, mantissa) = match self.0 {
+            Repr::Small(n) => return Score(Repr::Small(n / divisor as u128)),
+            Repr::Big { exponent, mantissa } => (exponent, mantissa),
+        };
+        // Move up to 128 bits of the exponent into the dividend first so the
+        // quotient keeps its precision
+        let shift = exponent.min(128);
+        let (hi, lo) = widen(mantissa, shift);
+        let divisor = divisor as u128;
+        let (mut quotient, mut remainder) = ([0u64; 4], 0u128);
+        for (i, limb) in [hi >> 64, hi & u64::MAX as u128, lo >> 64, lo & u64::MAX as u128].into_iter().enumerate() {
+            let current = (remainder << 64) | limb;
+            quotient[i] = (current / divisor) as u64;
+            remainder = current % divisor;
+        }
+        let hi = (quotient[0] as u128) << 64 | quotient[1] as u128;
+        let lo = (quotient[2] as u128) << 64 | quotient[3] as u128;
+        normalize(exponent - shift, hi, lo)
+    }
+
+    // `mantissa << exponent`, `Small` being exponent 0
+    fn parts(&self) -> (u32, u128) {
+        match self.0 {
+            Repr::Small(n) => (0, n),
+            Repr::Big { exponent, mantissa } => (exponent, mantissa),
+        }
+    }
+}
+
+// `mantissa << shift` as the high and low halves of a 256 bit number, `shift`
+// at most 128
+fn widen(mantissa: u128, shift: u32) -> (u128, u128) {
+    match shift {
+        0 => (0, mantissa),
+        128 => (mantissa, 0),
+        _ => (mantissa >> (128 - shift), mantissa << shift),
+    }
+}
+
+// `(hi << 128 | lo) << exponent`, rounded down to 128 significant bits
+fn normalize(exponent: u32, hi: u128, lo: u128) -> Score {
+    if hi == 0 && lo == 0 {
+        return Score::zero();
+    }
+    if hi == 0 {
+        let spare = lo.leading_zeros();
+        return match exponent <= spare {
+            true => Score(Repr::Small(lo << exponent)),
+            false => Score(Repr::Big { exponent: exponent - spare, mantissa: lo << spare }),
+        };
+    }
+    let shift = 128 - hi.leading_zeros();
+    let mantissa = match shift {
+        128 => hi,
+        _ => hi << (128 - shift) | lo >> shift,
+    };
+    Score(Repr::Big { exponent: exponent.saturating_add(shift), mantissa })
+}
+
+impl From<u128> for Score {
+    fn from(n: u128) -> Score {
+        Score(Repr::Small(n))
+    }
+}
+
+impl AddAssign<&Score> for Score {
+    fn add_assign(&mut self, other: &Score) {
+        if let (Repr::Small(a), Repr::Small(b)) = (&mut self.0, &other.0) {
+            if let Some(sum) = a.checked_add(*b) {
+                *a = sum;
+                return;
+            }
+        }
+        let (larger, smaller) = match self.parts() >= other.parts() {
+            true => (self.parts(), other.parts()),
+            false => (other.parts(), self.parts()),
+        };
+        let gap = larger.0 - smaller.0;
+        // Too small to reach the larger one's 128 bits
+        if gap >= 128 && larger.1 != 0 {
+            *self = normalize(larger.0, 0, larger.1);
+            return;
+        }
+        let (hi, lo) = widen(larger.1, gap.min(128));
+        let (lo, carry) = lo.overflowing_add(smaller.1);
+        *self = normalize(smaller.0, hi + carry as u128, lo);
+    }
+}
+
+// Stops at zero, scores never go negative
+impl SubAssign<&BigInt> for Score {
+    fn sub_assign(&mut self, other: &BigInt) {
+        if let (Repr::Small(a), Some(b)) = (&mut self.0, other.to_u128()) {
+            *a = a.saturating_sub(b);
+            return;
+        }
+        *self = Score::from_bigint(&(self.to_bigint() - other)).unwrap_or_else(Score::zero);
+    }
+}
+
+// Compares with upgrade prices, which stay `BigInt`s, without allocating
+// unless both are past `u128` and the same length
+impl PartialEq<BigInt> for Score {
+    fn eq(&self, other: &BigInt) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd<BigInt> for Score {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        if other.is_negative() {
+            return Some(Ordering::Greater);
+        }
+        match (&self.0, other.to_u128()) {
+            (Repr::Small(a), Some(b)) => Some(a.cmp(&b)),
+            (Repr::Small(_), None) => Some(Ordering::Less),
+            (Repr::Big { .. }, Some(_)) => Some(Ordering::Greater),
+            (Repr::Big { exponent, .. }, None) => match (*exponent as u64 + 128).cmp(&other.bits()) {
+                Ordering::Equal => Some(self.to_bigint().cmp(other)),
+                length => Some(length),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Repr::Small(n) => write!(f, "{}", n),
+            Repr::Big { .. } => write!(f, "{}", self.to_bigint()),
+        }
+    }
+}
+
+impl FromStr for Score {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Score, String> {
+        let n = BigInt::from_str(s).map_err(|e| e.to_string())?;
+        Score::from_bigint(&n).ok_or("negative".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::{game_logic::state::seeded_state, save::{load_text, save_text}};
+
+    // One past the largest `Small`
+    fn first_big() -> Score {
+        let mut score = Score::from(u128::MAX);
+        score += &Score::from(1);
+        score
+    }
+
+    fn is_big(score: &Score) -> bool {
+        matches!(score.0, Repr::Big { .. })
+    }
+
+    #[test]
+    fn adding_past_u128_moves_to_big() {
+        let score = first_big();
+        assert!(is_big(&score));
+        assert_eq!(score.to_bigint(), BigInt::from(u128::MAX) + 1);
+        assert!(score > Score::from(u128::MAX));
+        assert!(Score::from(u128::MAX) < score);
+    }
+
+    #[test]
+    fn multiplying_past_u128_moves_to_big() {
+        let score = Score::from(u128::MAX / 2 + 1).mul_u64(2);
+        assert_eq!(score, first_big());
+        assert!(is_big(&score));
+        assert_eq!(Score::from(u128::MAX / 3).mul_u64(3), Score::from(u128::MAX));
+    }
+
+    #[test]
+    fn shrinking_results_move_back_to_small() {
+        assert_eq!(first_big().mul_u64(0), Score::zero());
+        assert!(!is_big(&first_big().mul_u64(0)));
+        assert_eq!(first_big().div_u64(2), Score::from(u128::MAX / 2 + 1));
+        assert!(!is_big(&first_big().div_u64(2)));
+
+        let mut score = first_big();
+        score -= &BigInt::from(1);
+        assert_eq!(score, Score::from(u128::MAX));
+        assert!(!is_big(&score));
+        score -= &(BigInt::from(u128::MAX) * 2);
+        assert_eq!(score, Score::zero());
+    }
+
+    #[test]
+    fn compares_with_bigints_across_the_boundary() {
+        let max = BigInt::from(u128::MAX);
+        assert!(Score::from(u128::MAX) == max);
+        assert!(Score::from(u128::MAX) < max.clone() + 1);
+        assert!(first_big() == max.clone() + 1);
+        assert!(first_big() > max);
+        assert!(Score::zero() > BigInt::from(-1));
+    }
+
+    #[test]
+    fn displays_and_parses_at_the_boundary() {
+        assert_eq!(Score::from(u128::MAX).to_string(), "340282366920938463463374607431768211455");
+        assert_eq!(first_big().to_string(), "340282366920938463463374607431768211456");
+        for score in [Score::zero(), Score::from(u128::MAX), first_big(), first_big().mul_u64(1_000_000)] {
+            assert_eq!(Score::from_str(&score.to_string()), Ok(score.clone()));
+        }
+        assert!(!is_big(&Score::from_str("340282366920938463463374607431768211455").unwrap()));
+        assert!(Score::from_str("-1").is_err());
+    }
+
+    #[test]
+    fn saves_keep_the_whole_score() {
+        for score in [Score::from(12_345), Score::from(u128::MAX), first_big().mul_u64(u64::MAX)] {
+            let mut state = seeded_state(1);
+            state.score = score.clone();
+            let mut loaded = seeded_state(1);
+            load_text(&mut loaded, &save_text(&state)).unwrap();
+            assert_eq!(loaded.score, score);
+        }
+    }
+
+    // Deterministic numbers of every size up to 400 bits
+    fn test_numbers() -> Vec<BigInt> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut numbers = vec![BigInt::from(0), BigInt::from(1), BigInt::from(u128::MAX), BigInt::from(u128::MAX) + 1];
+        for bits in (1..400).step_by(7) {
+            let n: u128 = rng.gen();
+            numbers.push((BigInt::from(n) << bits) + rng.gen::<u64>());
+        }
+        numbers
+    }
+
+    // What every operation should give, the exact result rounded down
+    fn rounded(n: &BigInt) -> BigInt {
+        Score::from_bigint(n).unwrap().to_bigint()
+    }
+
+    #[test]
+    fn past_u128_keeps_the_top_128_bits() {
+        let n = (BigInt::from(u128::MAX) << 100) + 12345;
+        let score = Score::from_bigint(&n).unwrap();
+        assert_eq!(score.to_bigint(), BigInt::from(u128::MAX) << 100);
+        assert!(score.to_bigint() <= n);
+        // 1e100, the top of the speed upgrade prices
+        let price = BigInt::from(10).pow(100);
+        let error = price.clone() - Score::from_bigint(&price).unwrap().to_bigint();
+        assert!(error < price / BigInt::from(10).pow(38));
+    }
+
+    #[test]
+    fn operations_round_the_exact_result_down() {
+        let numbers = test_numbers();
+        for a in &numbers {
+            let score = Score::from_bigint(a).unwrap();
+            let a = score.to_bigint();
+            for b in &numbers {
+                let mut sum = score.clone();
+                let other = Score::from_bigint(b).unwrap();
+                sum += &other;
+                assert_eq!(sum.to_bigint(), rounded(&(&a + other.to_bigint())), "{} + {}", a, b);
+            }
+            for factor in [0u64, 1, 2, 3, 7, 1000, u64::MAX] {
+                assert_eq!(score.mul_u64(factor).to_bigint(), rounded(&(&a * factor)), "{} * {}", a, factor);
+            }
+            for divisor in [1u64, 2, 3, 7, 1000, u64::MAX] {
+                assert_eq!(score.div_u64(divisor).to_bigint(), rounded(&(&a / divisor)), "{} / {}", a, divisor);
+            }
+        }
+    }
+
+    #[test]
+    fn order_matches_the_numbers() {
+        let scores: Vec<Score> = test_numbers().iter().map(|n| Score::from_bigint(n).unwrap()).collect();
+        for a in &scores {
+            for b in &scores {
+                assert_eq!(a.cmp(b), a.to_bigint().cmp(&b.to_bigint()));
+                assert_eq!(a.partial_cmp(&b.to_bigint()), Some(a.to_bigint().cmp(&b.to_bigint())));
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_sales_leave_a_huge_score_alone() {
+        let mut score = Score::from_bigint(&BigInt::from(10).pow(60)).unwrap();
+        let before = score.clone();
+        score += &Score::from(1000);
+        assert_eq!(score, before);
+        score += &Score::from_bigint(&BigInt::from(10).pow(40)).unwrap();
+        assert!(score > before);
+    }
+
+    #[test]
+    fn converts_to_f64_past_u128() {
+        assert_eq!(first_big().to_f64(), 2f64.powi(128));
+        let huge = Score::from_bigint(&(BigInt::from(1) << 2000)).unwrap();
+        assert_eq!(huge.to_f64(), f64::MAX);
+    }
+}
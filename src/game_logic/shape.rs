@@ -1,50 +1,175 @@
-use num::{BigInt, FromPrimitive};
 use raylib::prelude::Color;
 
-use crate::drawing::HEIGHT;
+use super::world::WORLD_HEIGHT;
 
-use super::{state::{GameState, Shape}, upgrades::Upgrade};
+use super::{state::{GameState, Shape}, events::GameEvent, prices::{MAX_SIDES, price_table}, score::Score};
+use super::multipliers::active_multipliers;
+use super::playfield::DRAG_FOLLOW;
+use super::physics::physics;
+use super::walls::{MAX_RESTING, collide_tick};
+use super::laser::{CutMode, Laser, active_lasers, center_crossing, slice, swept_hit};
 
-pub fn shape_tick(state: &mut GameState, dt: u64) {
-    let dt = dt as f64 / 1000f64;
-    let gravity = 90f64;
+// Distance from a shape's center to its corners
+pub const SHAPE_RADIUS: f64 = 25f64;
+
+const SHAPE_NAMES: [&str; 6] = ["Triangle", "Square", "Pentagon", "Hexagon", "Heptagon", "Octagon"];
+
+pub fn shape_name(sides: i32) -> String {
+    match SHAPE_NAMES.get((sides - 3).max(0) as usize) {
+        Some(name) if sides >= 3 => name.to_string(),
+        _ => format!("{}-gon", sides),
+    }
+}
+
+pub fn shape_tick(state: &mut GameState, dt_ms: u64) {
+    let dt = dt_ms as f64 / 1000f64;
+    let physics = physics(state);
+    let drag_target = state.drag.map(|d| d.target);
     for shape in &mut state.shapes {
+        shape.prev_x = shape.x;
+        shape.prev_y = shape.y;
+        if let (true, Some((target_x, target_y))) = (shape.held, drag_target) {
+            shape.vel_x = (target_x - shape.x) / DRAG_FOLLOW;
+            shape.vel_y = (target_y - shape.y) / DRAG_FOLLOW;
+            shape.x += shape.vel_x*dt;
+            shape.y += shape.vel_y*dt;
+            continue;
+        }
         shape.x += shape.vel_x*dt;
         shape.y += shape.vel_y*dt;
-        shape.vel_y += dt*gravity;
-        shape.vel_x *= (1f64-dt*4f64).max(0f64);
+        shape.vel_y = (shape.vel_y + dt*physics.gravity).min(physics.terminal_velocity);
+        if !shape.sliding {
+            shape.vel_x *= (1f64-dt*physics.drag).max(0f64);
+        }
         shape.rot += shape.rot_vel*dt as f32;
     } 
-    if state.upgrades.contains(&Upgrade::ShapeLaser(1)) {
-        for i in 0..state.shapes.len() {
-            if state.shapes[i].laser_cuts == 0 
-                && state.shapes[i].y as i32 > HEIGHT/2 + 150 
-            {
-                state.shapes[i].laser_cuts += 1;
-                state.shapes[i].sides += 1;
-            }
+    collide_tick(state, dt_ms);
+    for (bit, laser) in active_lasers(state) {
+        match state.cut_mode {
+            CutMode::AddSide => add_side_tick(state, bit, &laser),
+            CutMode::Slice => slice_tick(state, bit, &laser),
         }
     }
+    let multipliers = active_multipliers(state);
+    let (score, events) = (&mut state.score, &mut state.events);
+    state.shapes.retain(|shape| {
+        // Shapes that came to rest on a bumper or ramp are sold where they lie
+        if shape.y <= WORLD_HEIGHT as f64 + 100f64 && shape.resting < MAX_RESTING {
+            return true;
+        }
+        // Missed golden shapes just fall away
+        if shape.golden {
+            return false;
+        }
+        // Colors are checked before shapes get into the game and cuts keep
+        // the side count within the price table, every shape has a price
+        let price = price_table().sale_price(shape, &multipliers).unwrap_or(Score::zero());
+        *score += &price;
+        events.push(GameEvent::ShapeSold { 
+            sides: shape.sides(), 
+            color: shape.color, 
+            price, 
+            x: shape.x, 
+            y: shape.y 
+        });
+        false
+    });
+}
+
+fn add_side_tick(state: &mut GameState, bit: u32, laser: &Laser) {
+    for shape in &mut state.shapes {
+        if !shape.golden && shape.laser_cuts & bit == 0 && swept_hit(laser, shape) {
+            shape.laser_cuts |= bit;
+            shape.vertices = regular_polygon((shape.sides() + 1).min(MAX_SIDES));
+            state.events.push(GameEvent::ShapeCut { sides: shape.sides(), x: shape.x, y: shape.y });
+        }
+    }
+}
+
+fn slice_tick(state: &mut GameState, bit: u32, laser: &Laser) {
     for i in (0..state.shapes.len()).rev() {
-        if state.shapes[i].y > HEIGHT as f64 + 100f64 {
-            state.score += shape_price(&state.shapes[i]);
-            state.shapes.remove(i);
-        }
-    }
-}
-
-fn shape_price(shape: &Shape) -> BigInt {
-    let side_level = (shape.sides - 2) as u32;
-    let side_price = BigInt::from_u64(500).unwrap().pow(side_level) 
-        - BigInt::from_u64(470).unwrap();
-    let color_multiplier = match shape.color {
-        Color::GRAY => BigInt::from_u64(1).unwrap(),
-        Color::RED => BigInt::from_u64(10).unwrap(),
-        Color::GREEN => BigInt::from_u64(100).unwrap(),
-        Color::BLUE => BigInt::from_u64(1000).unwrap(),
-        Color::YELLOW => BigInt::from_u64(10_000).unwrap(),
-        Color::PURPLE =>  BigInt::from_u64(100_000).unwrap(),
-        _ => BigInt::from_u64(0).unwrap()
-    };
-    return side_price * color_multiplier;
-}
\ No newline at end of file
+        if state.shapes[i].golden || state.shapes[i].laser_cuts & bit != 0 {
+            continue;
+        }
+        let (x, y) = match center_crossing(laser, &state.shapes[i]) {
+            Some(point) => point,
+            None => continue,
+        };
+        state.shapes[i].laser_cuts |= bit;
+        state.events.push(GameEvent::ShapeCut { sides: state.shapes[i].sides(), x, y });
+        if let Some(pieces) = slice(laser, &state.shapes[i], x, y) {
+            state.shapes.splice(i..=i, pieces);
+        }
+    }
+}
+
+// A regular polygon of `sides` corners, the first one straight below the
+// center like raylib's `draw_poly`
+pub fn regular_polygon(sides: i32) -> Vec<(f64, f64)> {
+    let step = 360f64 / sides as f64;
+    (0..sides)
+        .map(|i| {
+            let angle = (step * i as f64).to_radians();
+            (angle.sin() * SHAPE_RADIUS, angle.cos() * SHAPE_RADIUS)
+        })
+        .collect()
+}
+
+// An unrotated shape at rest, callers fill in the motion
+pub fn regular_shape(sides: i32, color: Color, x: f64, y: f64) -> Shape {
+    Shape {
+        x,
+        y,
+        prev_x: x,
+        prev_y: y,
+        vel_x: 0f64,
+        vel_y: 0f64,
+        rot: 0f32,
+        rot_vel: 0f32,
+        vertices: regular_polygon(sides),
+        color,
+        laser_cuts: 0,
+        golden: false,
+        held: false,
+        sliding: false,
+        resting: 0,
+    }
+}
+
+impl Shape {
+    pub fn sides(&self) -> i32 {
+        self.vertices.len() as i32
+    }
+
+    // The vertices rotated by `rot` and centered on `x`, `y`
+    pub fn corners_at(&self, x: f64, y: f64) -> Vec<(f64, f64)> {
+        let (sin, cos) = (self.rot as f64).to_radians().sin_cos();
+        self.vertices.iter()
+            .map(|(vx, vy)| (x + vx*cos + vy*sin, y - vx*sin + vy*cos))
+            .collect()
+    }
+
+    // Even-odd test against the polygon as drawn
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let corners = self.corners_at(self.x, self.y);
+        let mut inside = false;
+        for i in 0..corners.len() {
+            let (x1, y1) = corners[i];
+            let (x2, y2) = corners[(i + 1) % corners.len()];
+            if (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+pub fn polygon_area(points: &[(f64, f64)]) -> f64 {
+    let mut twice_area = 0f64;
+    for i in 0..points.len() {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % points.len()];
+        twice_area += x1*y2 - x2*y1;
+    }
+    twice_area.abs() / 2f64
+}
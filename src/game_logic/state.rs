@@ -1,23 +1,52 @@
-use num::{BigInt, FromPrimitive};
+use std::collections::VecDeque;
+
+use rand::{SeedableRng, rngs::StdRng};
 use raylib::prelude::Color;
 
+use super::action::Action;
+use super::achievements::{Achievement, AchievementNotification};
+use super::boosts::{Boost, GOLDEN_INTERVAL};
+use super::playfield::{Drag, ShapeInspection};
+use super::emission::ColorEmission;
+use super::walls::Segment;
+use super::events::GameEvent;
+use super::laser::{CutMode, DEFAULT_LASERS, Laser};
+use super::physics::{DEFAULT_PHYSICS, Physics};
+use super::score::Score;
 use super::upgrades::{Upgrade, PurchasableUpgrade};
 
+#[derive(Clone)]
 pub struct Shape {
     pub x: f64,
     pub y: f64,
     pub vel_x: f64,
     pub vel_y: f64,
+    // Position before the last step, the renderer blends towards `x`/`y`
+    pub prev_x: f64,
+    pub prev_y: f64,
 
     pub rot: f32,
     pub rot_vel: f32,
 
-    pub sides: i32,
+    // Corners around `x`, `y` before rotating by `rot`. Regular polygons until
+    // a laser slices them, see `shape::regular_shape`.
+    pub vertices: Vec<(f64, f64)>,
     pub color: Color,
 
-    pub laser_cuts: i32,
+    // One bit per laser that already cut the shape, see `laser::active_lasers`
+    pub laser_cuts: u32,
+    // Worth nothing when sold, clicking it starts a boost, see `boosts`
+    pub golden: bool,
+    // Being dragged with the mouse, see `playfield::grab`
+    pub held: bool,
+    // Touched a wall, bumper or ramp last step. Sliding shapes aren't slowed by
+    // air drag, see `walls::collide_tick`.
+    pub sliding: bool,
+    // Milliseconds spent sliding this slowly, see `walls::MAX_RESTING`
+    pub resting: u64,
 }
 
+#[derive(Clone)]
 pub struct GamePipe {
     // Number of sides for generated shape
     pub sides: i32,
@@ -26,28 +55,94 @@ pub struct GamePipe {
     pub last_drop: u64,
 }
 
+#[derive(Clone)]
 pub struct GameState {
     pub pipes: Vec<GamePipe>,
     pub shapes: Vec<Shape>,
     pub last_tick: u64,
-    pub score: BigInt,
+    // Wall clock milliseconds not yet simulated, always less than one step
+    pub step_accumulator: u64,
+    pub score: Score,
     pub upgrades: Vec<Upgrade>,
     pub available_upgrades: Vec<PurchasableUpgrade>,
-    pub mouse_click_x: i32,
-    pub mouse_click_y: i32
+    pub actions: VecDeque<Action>,
+    pub paused: bool,
+    // Index into `available_upgrades` of the keyboard/gamepad cursor
+    pub shop_selection: usize,
+    // Index of the first upgrade shown in the shop
+    pub shop_scroll: usize,
+    // How many upgrades fit in the shop, set by the renderer as the window resizes
+    pub shop_rows: usize,
+    pub events: Vec<GameEvent>,
+    pub achievements: Vec<Achievement>,
+    pub achievement_notifications: Vec<AchievementNotification>,
+    pub show_achievements: bool,
+    // Multiplies the time passing each tick, only changed by the dev console
+    pub time_scale: f64,
+    // What lasers do to the shapes they hit, kept in the save
+    pub cut_mode: CutMode,
+    // Running boosts, at most one of each kind
+    pub boosts: Vec<Boost>,
+    // Milliseconds of game time until the next golden shape drops
+    pub next_golden: u64,
+    pub drag: Option<Drag>,
+    // Stats of the last shape clicked on the playfield
+    pub inspection: Option<ShapeInspection>,
+    // Color mix of every pipe, kept in the save
+    pub emission: Vec<ColorEmission>,
+    // Pipe whose detail panel is open
+    pub pipe_panel: Option<usize>,
+    // Bumpers and ramps the player put on the playfield, kept in the save
+    pub placed_segments: Vec<Segment>,
+    // Where the right button went down for the next bumper or ramp
+    pub segment_start: Option<(f64, f64)>,
+    // From assets/physics.txt before upgrades, see `physics::physics`
+    pub physics: Physics,
+    // From assets/lasers.txt, see `laser::active_lasers`
+    pub lasers: Vec<(u64, Laser)>,
+    // All randomness in the game logic comes from `rng`, seeded with `seed`,
+    // so a replay of the same actions ends in the same state
+    pub seed: u64,
+    pub rng: StdRng,
 }
 
 pub fn initial_state()-> GameState {
+    seeded_state(rand::random())
+}
+
+pub fn seeded_state(seed: u64) -> GameState {
     GameState { 
         pipes: vec![
             GamePipe { sides: 3, color: Color::GRAY, speed: 1, last_drop: 0 },
         ],
         last_tick: 0,
+        step_accumulator: 0,
         shapes: vec!(),
-        score: BigInt::from_u64(0u64).unwrap(),
+        score: Score::zero(),
         upgrades: vec!(),
         available_upgrades: vec!(),
-        mouse_click_x: 0,
-        mouse_click_y: 0,
+        actions: VecDeque::new(),
+        paused: false,
+        shop_selection: 0,
+        shop_scroll: 0,
+        shop_rows: 1,
+        events: vec!(),
+        achievements: vec!(),
+        achievement_notifications: vec!(),
+        show_achievements: false,
+        time_scale: 1f64,
+        cut_mode: CutMode::AddSide,
+        boosts: vec!(),
+        next_golden: GOLDEN_INTERVAL.start,
+        drag: None,
+        inspection: None,
+        emission: vec!(),
+        pipe_panel: None,
+        placed_segments: vec!(),
+        segment_start: None,
+        physics: DEFAULT_PHYSICS,
+        lasers: DEFAULT_LASERS.to_vec(),
+        seed,
+        rng: StdRng::seed_from_u64(seed),
     }
 }
\ No newline at end of file
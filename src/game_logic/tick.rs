@@ -1,17 +1,65 @@
 use super::state::GameState;
+use super::action::{action_tick, clamp_shop_cursor};
 use super::pipe::pipe_tick;
 use super::shape::shape_tick;
 use super::upgrades::upgrade_tick;
+use super::achievements::achievements_tick;
+use super::boosts::boost_tick;
+use super::playfield::playfield_tick;
 
-pub fn tick(state: &mut GameState) {
+// Milliseconds of wall clock time since the last call, for the next `tick`
+pub fn elapsed(state: &mut GameState) -> u64 {
     let now = super::time::current_time();
-    let mut dt = now - state.last_tick;
+    // The wall clock can jump back, e.g. on a time zone or NTP change, and
+    // forward after a sleep. Either way don't simulate more than a catch up.
+    let mut dt = now.saturating_sub(state.last_tick).min(MAX_CATCH_UP);
     if state.last_tick == 0 {
         dt = 1000 / 30
     }
+    state.last_tick = now;
+    dt
+}
+
+// Milliseconds the simulation advances per `tick`. Small enough that no shape
+// moves past the laser line or out of a pipe's drop spot within one step.
+pub const STEP: u64 = 10;
+// Wall clock time caught up in a single frame. Anything beyond it, like a
+// window drag or a breakpoint, is dropped instead of simulated in a burst.
+const MAX_CATCH_UP: u64 = 250;
+
+// How many `STEP` ticks to run for `dt` milliseconds of wall clock time, the
+// remainder carries over to the next frame
+pub fn steps(state: &mut GameState, dt: u64) -> u32 {
+    let scaled = (dt.min(MAX_CATCH_UP) as f64 * state.time_scale) as u64;
+    state.step_accumulator += scaled;
+    let steps = state.step_accumulator / STEP;
+    state.step_accumulator %= STEP;
+    steps as u32
+}
+
+// How far the wall clock is between the last step and the next one, from 0 to
+// 1, for the renderer to blend shape positions
+pub fn interpolation(state: &GameState) -> f64 {
+    if state.paused {
+        return 1f64;
+    }
+    state.step_accumulator as f64 / STEP as f64
+}
+
+// Advances the game by `dt` milliseconds, normally `STEP`. Only depends on the
+// state, its RNG and the queued actions, which is what replays rely on.
+pub fn tick(state: &mut GameState, dt: u64) {
+    state.events.clear();
+    action_tick(state);
+    if state.paused {
+        return;
+    }
+    boost_tick(state, dt);
     pipe_tick(state, dt);
     shape_tick(state, dt);
     upgrade_tick(state);
-    state.last_tick = now;
+    clamp_shop_cursor(state);
+    achievements_tick(state, dt);
+    playfield_tick(state, dt);
 }
 
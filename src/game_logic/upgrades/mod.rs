@@ -1,14 +1,18 @@
 pub mod unlock_upgrades;
+mod overrides;
 mod purchase;
+pub mod pricing;
 
-use std::rc::Rc;
+use std::{fmt, rc::Rc, str::FromStr};
 
 use num::BigInt;
 use raylib::prelude::Color;
 
-use self::{unlock_upgrades::{UpgradeRequirement, unlock_upgrades_tick}, purchase::upgrade_purchase_tick};
+use self::{pricing::PriceCurve, unlock_upgrades::{UpgradeRequirement, unlock_upgrades_tick}};
+pub use self::overrides::load_upgrade_overrides;
+pub use self::purchase::{purchase_levels, purchase_upgrade};
 
-use super::state::GameState;
+use super::{state::GameState, colors::{color_name, color_from_name}};
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum Upgrade {
@@ -28,6 +32,110 @@ pub enum Upgrade {
     Pipe4Color(Color),
 
     ShapeLaser(u64),
+
+    // Sale bonuses, see `multipliers`
+    SaleMultiplier(u64),
+    SidesMultiplier(i32),
+    ColorMultiplier(Color),
+
+    // Slows falling shapes, see `physics`
+    AntiGravity(u64),
+    // Bumpers and ramps that can be placed, see `walls`
+    Bumpers(u64),
+}
+
+impl Upgrade {
+    // The color a pipe color or color multiplier upgrade is about
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Upgrade::Pipe1Color(c) | Upgrade::Pipe2Color(c) | Upgrade::Pipe3Color(c) | Upgrade::Pipe4Color(c) => Some(*c),
+            Upgrade::ColorMultiplier(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    // The level of a pipe speed upgrade
+    pub fn speed_level(&self) -> Option<u64> {
+        match self {
+            Upgrade::Pipe1Speed(level) | Upgrade::Pipe2Speed(level) | Upgrade::Pipe3Speed(level) | Upgrade::Pipe4Speed(level) => Some(*level),
+            _ => None,
+        }
+    }
+
+    // The same pipe speed upgrade at another level
+    pub fn with_speed_level(&self, level: u64) -> Option<Upgrade> {
+        match self {
+            Upgrade::Pipe1Speed(_) => Some(Upgrade::Pipe1Speed(level)),
+            Upgrade::Pipe2Speed(_) => Some(Upgrade::Pipe2Speed(level)),
+            Upgrade::Pipe3Speed(_) => Some(Upgrade::Pipe3Speed(level)),
+            Upgrade::Pipe4Speed(_) => Some(Upgrade::Pipe4Speed(level)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Upgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = |c: &Color| color_name(*c).unwrap_or("Unknown");
+        match self {
+            Upgrade::Pipe1Speed(level) => write!(f, "Pipe1Speed({})", level),
+            Upgrade::Pipe1Color(c) => write!(f, "Pipe1Color({})", color(c)),
+            Upgrade::Pipe2 => write!(f, "Pipe2"),
+            Upgrade::Pipe2Speed(level) => write!(f, "Pipe2Speed({})", level),
+            Upgrade::Pipe2Color(c) => write!(f, "Pipe2Color({})", color(c)),
+            Upgrade::Pipe3 => write!(f, "Pipe3"),
+            Upgrade::Pipe3Speed(level) => write!(f, "Pipe3Speed({})", level),
+            Upgrade::Pipe3Color(c) => write!(f, "Pipe3Color({})", color(c)),
+            Upgrade::Pipe4 => write!(f, "Pipe4"),
+            Upgrade::Pipe4Speed(level) => write!(f, "Pipe4Speed({})", level),
+            Upgrade::Pipe4Color(c) => write!(f, "Pipe4Color({})", color(c)),
+            Upgrade::ShapeLaser(level) => write!(f, "ShapeLaser({})", level),
+            Upgrade::SaleMultiplier(level) => write!(f, "SaleMultiplier({})", level),
+            Upgrade::SidesMultiplier(sides) => write!(f, "SidesMultiplier({})", sides),
+            Upgrade::ColorMultiplier(c) => write!(f, "ColorMultiplier({})", color(c)),
+            Upgrade::AntiGravity(level) => write!(f, "AntiGravity({})", level),
+            Upgrade::Bumpers(level) => write!(f, "Bumpers({})", level),
+        }
+    }
+}
+
+impl FromStr for Upgrade {
+    type Err = String;
+
+    // Parses the format produced by `Display`, e.g. `Pipe3Speed(20)` or `Pipe1Color(Red)`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.find('(') {
+            Some(open) if s.ends_with(')') => (&s[..open], Some(&s[open + 1..s.len() - 1])),
+            _ => (s, None),
+        };
+        let level = || arg
+            .and_then(|a| a.trim().parse::<u64>().ok())
+            .ok_or(format!("Expected a level in '{}'", s));
+        let color = || arg
+            .and_then(|a| color_from_name(a.trim()))
+            .ok_or(format!("Expected a color in '{}'", s));
+        match name {
+            "Pipe1Speed" => Ok(Upgrade::Pipe1Speed(level()?)),
+            "Pipe1Color" => Ok(Upgrade::Pipe1Color(color()?)),
+            "Pipe2" => Ok(Upgrade::Pipe2),
+            "Pipe2Speed" => Ok(Upgrade::Pipe2Speed(level()?)),
+            "Pipe2Color" => Ok(Upgrade::Pipe2Color(color()?)),
+            "Pipe3" => Ok(Upgrade::Pipe3),
+            "Pipe3Speed" => Ok(Upgrade::Pipe3Speed(level()?)),
+            "Pipe3Color" => Ok(Upgrade::Pipe3Color(color()?)),
+            "Pipe4" => Ok(Upgrade::Pipe4),
+            "Pipe4Speed" => Ok(Upgrade::Pipe4Speed(level()?)),
+            "Pipe4Color" => Ok(Upgrade::Pipe4Color(color()?)),
+            "ShapeLaser" => Ok(Upgrade::ShapeLaser(level()?)),
+            "SaleMultiplier" => Ok(Upgrade::SaleMultiplier(level()?)),
+            "SidesMultiplier" => Ok(Upgrade::SidesMultiplier(level()? as i32)),
+            "ColorMultiplier" => Ok(Upgrade::ColorMultiplier(color()?)),
+            "AntiGravity" => Ok(Upgrade::AntiGravity(level()?)),
+            "Bumpers" => Ok(Upgrade::Bumpers(level()?)),
+            _ => Err(format!("Unknown upgrade '{}'", s)),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -35,10 +143,12 @@ pub struct PurchasableUpgrade {
     pub upgrade: Upgrade,
     pub price: BigInt,
     pub description: String,
+    // The curve `price` came from, shared by every level priced with it. None
+    // for hand picked and overridden prices.
+    curve: Option<Rc<PriceCurve>>,
     requirements: Vec<Rc<dyn UpgradeRequirement>>,
 }
 
 pub fn upgrade_tick(state: &mut GameState) {
     unlock_upgrades_tick(state);
-    upgrade_purchase_tick(state);
 }
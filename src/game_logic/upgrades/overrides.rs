@@ -0,0 +1,60 @@
+use std::{rc::Rc, str::FromStr};
+
+use num::BigInt;
+
+use super::{
+    PurchasableUpgrade,
+    Upgrade,
+    unlock_upgrades::{HasPipe, HasUpgrade, UpgradeRequirement, all_upgrades, set_upgrade_catalog},
+};
+
+// Rebuilds the catalog from the built in upgrades with the overrides in
+// `contents` applied. Lines look like `Pipe2 price = 20000`, see
+// assets/upgrades.txt. Bad lines are reported and skipped like in the settings.
+pub fn load_upgrade_overrides(contents: &str) {
+    let mut upgrades = all_upgrades();
+    for line in contents.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        if let Err(e) = apply_override(&mut upgrades, line) {
+            eprintln!("Ignoring upgrade override '{}': {}", line, e);
+        }
+    }
+    set_upgrade_catalog(upgrades);
+}
+
+fn apply_override(upgrades: &mut [PurchasableUpgrade], line: &str) -> Result<(), String> {
+    let (key, value) = line.split_once('=').ok_or("expected upgrade field = value")?;
+    let (upgrade, field) = key.trim().rsplit_once(' ').ok_or("expected upgrade field = value")?;
+    let upgrade = Upgrade::from_str(upgrade)?;
+    let value = value.trim();
+    let entry = upgrades.iter_mut()
+        .find(|u| u.upgrade == upgrade)
+        .ok_or(format!("'{}' is not in the catalog", upgrade))?;
+    match field {
+        "price" => {
+            entry.price = BigInt::from_str(&value.replace('_', ""))
+                .map_err(|_| format!("bad price '{}'", value))?;
+            entry.curve = None;
+        },
+        "description" => entry.description = value.to_string(),
+        "requires" => {
+            entry.requirements = value.split(',')
+                .map(|r| r.trim())
+                .filter(|r| !r.is_empty())
+                .map(parse_requirement)
+                .collect::<Result<_, _>>()?;
+        },
+        _ => return Err(format!("unknown field '{}'", field)),
+    }
+    Ok(())
+}
+
+// Either another upgrade or `pipes N`
+fn parse_requirement(requirement: &str) -> Result<Rc<dyn UpgradeRequirement>, String> {
+    match requirement.strip_prefix("pipes ") {
+        Some(count) => {
+            let count = count.trim().parse().map_err(|_| format!("bad pipe count '{}'", count))?;
+            Ok(HasPipe::new(count).rc())
+        },
+        None => Ok(HasUpgrade::new(Upgrade::from_str(requirement)?).rc()),
+    }
+}
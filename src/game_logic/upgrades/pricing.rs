@@ -0,0 +1,177 @@
+use num::{BigInt, BigRational, FromPrimitive, One, Zero};
+
+// Price of a levelled upgrade in exact arithmetic, so levels can go as high as
+// the catalog wants without rounding or overflowing:
+//
+//   price(level) = floor(scale * ratio^level) + constant + quadratic * level^2
+#[derive(Clone, Debug)]
+pub struct PriceCurve {
+    pub scale: BigRational,
+    pub ratio: BigRational,
+    pub constant: BigInt,
+    pub quadratic: BigInt,
+}
+
+// The pipe speed curve, 3.2 times pricier per level with `base` setting the
+// starting point of each pipe
+pub fn speed_curve(base: u64) -> PriceCurve {
+    PriceCurve {
+        scale: BigRational::from_integer(BigInt::from_u64(base).unwrap() * 12),
+        ratio: BigRational::new(BigInt::from_u64(16).unwrap(), BigInt::from_u64(5).unwrap()),
+        constant: BigInt::from_u64(40).unwrap(),
+        quadratic: BigInt::from_u64(10).unwrap(),
+    }
+}
+
+impl PriceCurve {
+    pub fn price(&self, level: u64) -> BigInt {
+        let level_big = BigInt::from_u64(level).unwrap();
+        let geometric = &self.scale * num::pow(self.ratio.clone(), level as usize);
+        geometric.floor().to_integer() + &self.constant + &self.quadratic * &level_big * &level_big
+    }
+
+    // Total for `count` levels starting at `from`. The geometric part is summed
+    // before flooring, so this can be up to `count - 1` more than adding up
+    // `price` level by level.
+    pub fn bulk_price(&self, from: u64, count: u64) -> BigInt {
+        if count == 0 {
+            return BigInt::zero();
+        }
+        let first = &self.scale * num::pow(self.ratio.clone(), from as usize);
+        let geometric = geometric_series(&first, &self.ratio, count);
+        let squares = sum_of_squares(from + count - 1) - sum_of_squares(from.saturating_sub(1));
+        geometric.floor().to_integer()
+            + &self.constant * BigInt::from_u64(count).unwrap()
+            + &self.quadratic * squares
+    }
+}
+
+// first + first * ratio + ... + first * ratio^(count - 1)
+pub fn geometric_series(first: &BigRational, ratio: &BigRational, count: u64) -> BigRational {
+    if ratio.is_one() {
+        return first * BigRational::from_integer(BigInt::from_u64(count).unwrap());
+    }
+    let one = BigRational::one();
+    first * (num::pow(ratio.clone(), count as usize) - &one) / (ratio - &one)
+}
+
+// 1^2 + 2^2 + ... + n^2
+fn sum_of_squares(n: u64) -> BigInt {
+    let n = BigInt::from_u64(n).unwrap();
+    &n * (&n + 1) * (&n * 2 + 1) / 6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::upgrades::unlock_upgrades::SPEED_LEVELS;
+
+    fn big(n: u64) -> BigInt {
+        BigInt::from_u64(n).unwrap()
+    }
+
+    #[test]
+    fn speed_curve_starts_cheap() {
+        let curve = speed_curve(1);
+        // floor(12 * 3.2) + 40 + 10 and floor(12 * 10.24) + 40 + 40
+        assert_eq!(curve.price(1), big(88));
+        assert_eq!(curve.price(2), big(202));
+    }
+
+    #[test]
+    fn speed_curve_is_exact_at_the_last_level() {
+        let curve = speed_curve(4_000_000);
+        let level = SPEED_LEVELS as u32;
+        let expected = big(4_000_000 * 12) * big(16).pow(level) / big(5).pow(level)
+            + 40
+            + big(10) * big(SPEED_LEVELS) * big(SPEED_LEVELS);
+        assert_eq!(curve.price(SPEED_LEVELS), expected);
+    }
+
+    // Far past the catalog, where floats would have long run out of range
+    #[test]
+    fn speed_curve_is_exact_hundreds_of_levels_in() {
+        let curve = speed_curve(200_000_000);
+        for level in [500u64, 750] {
+            let expected = big(200_000_000 * 12) * big(16).pow(level as u32) / big(5).pow(level as u32)
+                + 40
+                + big(10) * big(level) * big(level);
+            assert_eq!(curve.price(level), expected, "level {}", level);
+        }
+        assert!(curve.price(750) > big(10).pow(380));
+    }
+
+    #[test]
+    fn speed_curve_rises_every_level() {
+        for base in [1, 4_000_000] {
+            let curve = speed_curve(base);
+            for level in 1..=SPEED_LEVELS {
+                assert!(curve.price(level) > curve.price(level - 1), "base {} level {}", base, level);
+            }
+        }
+    }
+
+    fn ratio(numerator: u64, denominator: u64) -> BigRational {
+        BigRational::new(big(numerator), big(denominator))
+    }
+
+    #[test]
+    fn geometric_series_is_exact() {
+        // 3 + 6 + 12 + 24
+        assert_eq!(geometric_series(&ratio(3, 1), &ratio(2, 1), 4), ratio(45, 1));
+        // 8 + 4 + 2 + 1 + 1/2
+        assert_eq!(geometric_series(&ratio(8, 1), &ratio(1, 2), 5), ratio(31, 2));
+        // 5/3 + 16/3 + 256/15
+        assert_eq!(geometric_series(&ratio(5, 3), &ratio(16, 5), 3), ratio(25 + 80 + 256, 15));
+        assert_eq!(geometric_series(&ratio(7, 2), &ratio(1, 1), 6), ratio(21, 1));
+        assert_eq!(geometric_series(&ratio(7, 2), &ratio(16, 5), 0), ratio(0, 1));
+    }
+
+    #[test]
+    fn geometric_series_matches_adding_terms_up() {
+        let (first, step) = (ratio(12, 1), ratio(16, 5));
+        let mut term = first.clone();
+        let mut total = BigRational::zero();
+        for count in 1..=60 {
+            total += &term;
+            term *= &step;
+            assert_eq!(geometric_series(&first, &step, count), total, "count {}", count);
+        }
+    }
+
+    #[test]
+    fn sum_of_squares_counts_every_square() {
+        assert_eq!(sum_of_squares(0), big(0));
+        assert_eq!(sum_of_squares(1), big(1));
+        assert_eq!(sum_of_squares(3), big(14));
+        assert_eq!(sum_of_squares(40), (1..=40u64).map(|n| big(n * n)).sum::<BigInt>());
+    }
+
+    #[test]
+    fn bulk_price_of_one_level_is_its_price() {
+        let curve = speed_curve(4_000_000);
+        for level in [1, 2, 17, SPEED_LEVELS] {
+            assert_eq!(curve.bulk_price(level, 1), curve.price(level));
+        }
+        assert_eq!(curve.bulk_price(5, 0), big(0));
+    }
+
+    #[test]
+    fn bulk_price_rounds_down_once() {
+        let curve = speed_curve(1);
+        for (from, count) in [(1, 2), (1, SPEED_LEVELS), (10, 7), (300, 250)] {
+            let separately: BigInt = (from..from + count).map(|level| curve.price(level)).sum();
+            let bulk = curve.bulk_price(from, count);
+            assert!(bulk >= separately && bulk < separately.clone() + count, "from {} count {}", from, count);
+        }
+    }
+
+    #[test]
+    fn bulk_price_with_a_whole_ratio_adds_up_exactly() {
+        let curve = PriceCurve { scale: ratio(5, 1), ratio: ratio(3, 1), constant: big(7), quadratic: big(2) };
+        for (from, count) in [(0, 1), (1, 10), (4, 500)] {
+            let separately: BigInt = (from..from + count).map(|level| curve.price(level)).sum();
+            assert_eq!(curve.bulk_price(from, count), separately);
+        }
+    }
+}
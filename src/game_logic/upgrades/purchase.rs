@@ -1,35 +1,141 @@
-use crate::{drawing::WIDTH, game_logic::state::GameState};
+use std::rc::Rc;
 
-use super::PurchasableUpgrade;
+use num::BigInt;
 
-pub fn upgrade_purchase_tick(state: &mut GameState) {
-    if state.mouse_click_x != 0 || state.mouse_click_y != 0 {
-        let x = state.mouse_click_x;
-        let y = state.mouse_click_y;
-        state.mouse_click_x = 0;
-        state.mouse_click_y = 0;
-        on_upgrade_clicked(state, x, y);
+use crate::game_logic::{events::GameEvent, state::GameState};
+
+use super::{PurchasableUpgrade, unlock_upgrades::catalog_entry};
+
+// `index` refers to `available_upgrades` as it was last drawn, the list is
+// only rebuilt after all queued actions have been handled
+pub fn purchase_upgrade(state: &mut GameState, index: usize) {
+    let upgrade = match state.available_upgrades.get(index) {
+        Some(upgrade) => upgrade.clone(),
+        None => return,
+    };
+    if state.upgrades.contains(&upgrade.upgrade) {
+        return;
+    }
+    if state.score >= upgrade.price {
+        state.score -= &upgrade.price;
+        state.upgrades.push(upgrade.upgrade.clone());
+        state.events.push(GameEvent::UpgradePurchased { upgrade: upgrade.upgrade, index });
     }
 }
 
-fn on_upgrade_clicked(state: &mut GameState, x: i32, y: i32) {
-    if let Some(upgrade) = upgrade_from_coordinates(state, x, y) {
-        if upgrade.price <= state.score {
-            state.score -= upgrade.price;
-            state.upgrades.push(upgrade.upgrade);
+// Buys the pipe speed upgrade at `index` and up to `count - 1` levels after
+// it, all or nothing. The catalog's last level ends the run early. Anything
+// but a speed upgrade is bought on its own.
+pub fn purchase_levels(state: &mut GameState, index: usize, count: u64) {
+    let first = match state.available_upgrades.get(index) {
+        Some(upgrade) => upgrade.upgrade.clone(),
+        None => return,
+    };
+    let from = match first.speed_level() {
+        Some(level) => level,
+        None => return purchase_upgrade(state, index),
+    };
+    let levels: Vec<PurchasableUpgrade> = (from..from + count.max(1))
+        .map_while(|level| catalog_entry(&first.with_speed_level(level)?))
+        .take_while(|u| !state.upgrades.contains(&u.upgrade))
+        .collect();
+    if levels.is_empty() {
+        return;
+    }
+    let price = levels_price(&levels, from);
+    if state.score >= price {
+        state.score -= &price;
+        for level in levels {
+            state.upgrades.push(level.upgrade.clone());
+            state.events.push(GameEvent::UpgradePurchased { upgrade: level.upgrade, index });
         }
     }
 }
 
-fn upgrade_from_coordinates(state: &mut GameState, x: i32, y: i32) -> Option<PurchasableUpgrade> {
-    for (i, upgrade) in state.available_upgrades.iter().enumerate() {
-        let button_x = WIDTH - 50 - 32 + 10;
-        let button_y: i32 = 32 + 24 + 12 +  32 + 12 + i as i32 * (36 + 12);
-        let width = 50;
-        let height = 36;
-        if (button_x..(button_x+width)).contains(&x) && (button_y..(button_y+height)).contains(&y) {
-            return Some(upgrade.clone());
-        }
+// One geometric series while the levels are all priced by the same curve,
+// otherwise their catalog prices added up
+fn levels_price(levels: &[PurchasableUpgrade], from: u64) -> BigInt {
+    let same_curve = |u: &PurchasableUpgrade| match (&u.curve, &levels[0].curve) {
+        (Some(curve), Some(first)) => Rc::ptr_eq(curve, first),
+        _ => false,
+    };
+    match &levels[0].curve {
+        Some(curve) if levels.iter().all(same_curve) => curve.bulk_price(from, levels.len() as u64),
+        _ => levels.iter().map(|u| &u.price).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{
+        score::Score,
+        state::seeded_state,
+        upgrades::{Upgrade, load_upgrade_overrides, pricing::speed_curve, unlock_upgrades::{SPEED_LEVELS, unlock_upgrades_tick}},
+    };
+
+    fn shop_index(state: &GameState, upgrade: &Upgrade) -> usize {
+        state.available_upgrades.iter().position(|u| u.upgrade == *upgrade).unwrap()
+    }
+
+    fn state_with_score(score: &BigInt) -> GameState {
+        let mut state = seeded_state(1);
+        state.score = Score::from_bigint(score).unwrap();
+        unlock_upgrades_tick(&mut state);
+        state
+    }
+
+    #[test]
+    fn several_levels_cost_their_bulk_price() {
+        let price = speed_curve(1).bulk_price(1, 5);
+        let mut state = state_with_score(&(price.clone() + 3));
+        let index = shop_index(&state, &Upgrade::Pipe1Speed(1));
+        purchase_levels(&mut state, index, 5);
+        assert_eq!(state.upgrades, (1..=5).map(Upgrade::Pipe1Speed).collect::<Vec<Upgrade>>());
+        assert_eq!(state.score, Score::from(3));
+        assert_eq!(state.events.len(), 5);
+    }
+
+    #[test]
+    fn unaffordable_runs_buy_nothing() {
+        let price = speed_curve(1).bulk_price(1, 5);
+        let mut state = state_with_score(&(price - 1));
+        let index = shop_index(&state, &Upgrade::Pipe1Speed(1));
+        purchase_levels(&mut state, index, 5);
+        assert!(state.upgrades.is_empty());
+        assert!(state.events.is_empty());
+    }
+
+    #[test]
+    fn runs_stop_at_the_last_level() {
+        let mut state = seeded_state(1);
+        state.upgrades = (1..SPEED_LEVELS - 1).map(Upgrade::Pipe1Speed).collect();
+        state.score = Score::from_bigint(&speed_curve(1).bulk_price(1, SPEED_LEVELS)).unwrap();
+        unlock_upgrades_tick(&mut state);
+        let index = shop_index(&state, &Upgrade::Pipe1Speed(SPEED_LEVELS - 1));
+        purchase_levels(&mut state, index, 10);
+        assert_eq!(state.upgrades.last(), Some(&Upgrade::Pipe1Speed(SPEED_LEVELS)));
+        assert_eq!(state.upgrades.len() as u64, SPEED_LEVELS);
+    }
+
+    #[test]
+    fn overridden_levels_are_added_up() {
+        load_upgrade_overrides("Pipe1Speed(2) price = 1000\n");
+        let expected = speed_curve(1).price(1) + 1000 + speed_curve(1).price(3);
+        let mut state = state_with_score(&expected);
+        let index = shop_index(&state, &Upgrade::Pipe1Speed(1));
+        purchase_levels(&mut state, index, 3);
+        load_upgrade_overrides("");
+        assert_eq!(state.upgrades.len(), 3);
+        assert_eq!(state.score, Score::from(0));
+    }
+
+    #[test]
+    fn other_upgrades_are_bought_once() {
+        let mut state = state_with_score(&BigInt::from(10u64).pow(12));
+        let index = state.available_upgrades.iter().position(|u| u.upgrade.speed_level().is_none()).unwrap();
+        let upgrade = state.available_upgrades[index].upgrade.clone();
+        purchase_levels(&mut state, index, 10);
+        assert_eq!(state.upgrades, vec![upgrade]);
     }
-    None
 }
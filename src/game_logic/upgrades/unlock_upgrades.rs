@@ -1,12 +1,10 @@
-use std::{rc::Rc};
+use std::{cell::RefCell, rc::Rc};
 
 use num::{BigInt, FromPrimitive};
-use once_cell::unsync::Lazy;
-use raylib::prelude::Color;
 
-use crate::game_logic::state::GameState;
+use crate::game_logic::{state::GameState, colors::SHAPE_COLORS, physics::ANTI_GRAVITY_LEVELS, prices::price_table, shape::shape_name, walls::BUMPER_LEVELS};
 
-use super::{PurchasableUpgrade, Upgrade};
+use super::{PurchasableUpgrade, Upgrade, pricing::speed_curve};
 
 pub trait UpgradeRequirement {
     fn unlockable(&self, state: &mut GameState) -> bool;
@@ -49,63 +47,103 @@ impl UpgradeRequirement for HasUpgrade {
 }
 
 pub fn unlock_upgrades_tick(state: &mut GameState) {
-    state.available_upgrades = ALL_UPGRADES_MEMO
-        .iter()
-        .cloned()
-        .filter(
-            |u| !state.upgrades.contains(&u.upgrade) 
-                && u.requirements.iter().all(|r| r.unlockable(state)))     
-        .collect();
+    state.available_upgrades = ALL_UPGRADES_MEMO.with(|all_upgrades| {
+        all_upgrades
+            .borrow()
+            .iter()
+            .filter(
+                |u| !state.upgrades.contains(&u.upgrade) 
+                    && u.requirements.iter().all(|r| r.unlockable(state)))     
+            .cloned()
+            .collect()
+    });
     state.available_upgrades
         .sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
 }
 
-const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
+thread_local! {
+    static ALL_UPGRADES_MEMO: RefCell<Vec<PurchasableUpgrade>> = RefCell::new(priced_upgrades(all_upgrades()));
+}
+
+// The catalog entry of `upgrade`, with any overrides applied
+pub fn catalog_entry(upgrade: &Upgrade) -> Option<PurchasableUpgrade> {
+    ALL_UPGRADES_MEMO.with(|all_upgrades| all_upgrades.borrow().iter().find(|u| u.upgrade == *upgrade).cloned())
+}
+
+// Replaces the catalog used from the next tick on, see `load_upgrade_overrides`
+pub fn set_upgrade_catalog(upgrades: Vec<PurchasableUpgrade>) {
+    let upgrades = priced_upgrades(upgrades);
+    ALL_UPGRADES_MEMO.with(|all_upgrades| *all_upgrades.borrow_mut() = upgrades);
+}
+
+// Leaves out color upgrades for colors the price table doesn't know, which
+// would otherwise drop shapes worth nothing
+fn priced_upgrades(upgrades: Vec<PurchasableUpgrade>) -> Vec<PurchasableUpgrade> {
+    upgrades.into_iter()
+        .filter(|u| match u.upgrade.color().map(|c| price_table().check_color(c)) {
+            Some(Err(e)) => {
+                eprintln!("Leaving {} out of the upgrade catalog: {}", u.upgrade, e);
+                false
+            },
+            _ => true,
+        })
+        .collect()
+}
+
+// Levels of each pipe speed upgrade in the catalog
+pub const SPEED_LEVELS: u64 = 40;
+const SALE_MULTIPLIER_LEVELS: u64 = 5;
+
+// 100^(i + 1) + 700 for the `i`th color of a pipe
+fn color_price(i: usize) -> BigInt {
+    BigInt::from_u64(100).unwrap().pow(i as u32 + 1) + 700
+}
+
+pub fn all_upgrades() -> Vec<PurchasableUpgrade> {
     let mut upgrades = vec![
         PurchasableUpgrade {
             upgrade: Upgrade::Pipe2,
             price: BigInt::from_u64(10_000).unwrap(),
             description: "Square Pipe".to_owned(),
+            curve: None,
             requirements: vec![HasUpgrade::new(Upgrade::Pipe1Speed(3)).rc()]
         },
         PurchasableUpgrade {
             upgrade: Upgrade::Pipe3,
             price: BigInt::from_u64(10_000_000).unwrap(),
             description: "Pentagon Pipe".to_owned(),
+            curve: None,
             requirements: vec![HasPipe::new(2).rc()]
         },
         PurchasableUpgrade {
             upgrade: Upgrade::Pipe4,
             price: BigInt::from_u64(10_000_000_000).unwrap(),
             description: "Hexagon Pipe".to_owned(),
+            curve: None,
             requirements: vec![HasPipe::new(3).rc()]
         },
         PurchasableUpgrade {
             upgrade: Upgrade::ShapeLaser(1),
             price: BigInt::from_u64(500_000_000_000_000_000).unwrap(),
             description: "Shape Cutting Laser".to_owned(),
+            curve: None,
             requirements: vec![HasPipe::new(3).rc()]
         }
     ];
 
-    let shape_colors: Vec<(Color, &'static str)> = vec![
-        (Color::RED, "Red"),
-        (Color::GREEN, "Green"),
-        (Color::BLUE, "Blue"),
-        (Color::YELLOW, "Yellow"),
-        (Color::PURPLE, "Purple")
-    ];
+    let shape_colors = SHAPE_COLORS;
 
     for (i, c) in shape_colors.iter().enumerate() {
         upgrades.push(PurchasableUpgrade {
-            upgrade: Upgrade::Pipe1Color(c.0.clone()),
-            price: BigInt::from_u64(100u64.pow(i as u32 + 1) + 700).unwrap(),
+            upgrade: Upgrade::Pipe1Color(c.0),
+            price: color_price(i),
             description: format!("Triangle Pipe {}", c.1),
+            curve: None,
             requirements: match i {
                 0 => vec![],
                 n => vec![
                     HasUpgrade::new(
-                        Upgrade::Pipe1Color(shape_colors[n - 1].0.clone())
+                        Upgrade::Pipe1Color(shape_colors[n - 1].0)
                     ).rc()
                 ]
             }
@@ -114,15 +152,16 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
 
     for (i, c) in shape_colors.iter().enumerate() {
         upgrades.push(PurchasableUpgrade {
-            upgrade: Upgrade::Pipe2Color(c.0.clone()),
+            upgrade: Upgrade::Pipe2Color(c.0),
             price: BigInt::from_u64(50_000).unwrap()
-                * BigInt::from_u64(100u64.pow(i as u32 + 1) + 700).unwrap(),
+                * color_price(i),
             description: format!("Square Pipe {}", c.1),
+            curve: None,
             requirements: match i {
                 0 => vec![HasPipe::new(2).rc()],
                 n => vec![
                     HasUpgrade::new(
-                        Upgrade::Pipe2Color(shape_colors[n - 1].0.clone())
+                        Upgrade::Pipe2Color(shape_colors[n - 1].0)
                     ).rc()
                 ]
             }
@@ -131,15 +170,16 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
 
     for (i, c) in shape_colors.iter().enumerate() {
         upgrades.push(PurchasableUpgrade {
-            upgrade: Upgrade::Pipe3Color(c.0.clone()),
+            upgrade: Upgrade::Pipe3Color(c.0),
             price: BigInt::from_u64(1_000_000).unwrap() 
-                * BigInt::from_u64(100u64.pow(i as u32 + 1) + 700).unwrap(),
+                * color_price(i),
             description: format!("Pentagon Pipe {}", c.1),
+            curve: None,
             requirements: match i {
                 0 => vec![HasPipe::new(3).rc()],
                 n => vec![
                     HasUpgrade::new(
-                        Upgrade::Pipe3Color(shape_colors[n - 1].0.clone())
+                        Upgrade::Pipe3Color(shape_colors[n - 1].0)
                     ).rc()]
             }
         });
@@ -147,27 +187,28 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
 
     for (i, c) in shape_colors.iter().enumerate() {
         upgrades.push(PurchasableUpgrade {
-            upgrade: Upgrade::Pipe4Color(c.0.clone()),
+            upgrade: Upgrade::Pipe4Color(c.0),
             price: BigInt::from_u64(100_000_000).unwrap() 
-                * BigInt::from_u64(100u64.pow(i as u32 + 1) + 700).unwrap(),
+                * color_price(i),
             description: format!("Hexagon Pipe {}", c.1),
+            curve: None,
             requirements: match i {
                 0 => vec![HasPipe::new(4).rc()],
                 n => vec![
                     HasUpgrade::new(
-                        Upgrade::Pipe3Color(shape_colors[n - 1].0.clone())
+                        Upgrade::Pipe3Color(shape_colors[n - 1].0)
                     ).rc()]
             }
         });
     }
 
-    for i in 1..=40 {
-        let if64 = i as f64;
-        let cost = 3.2f64.powf(if64)*12f64 + 40f64 + 10f64*if64*if64;
+    let curve = Rc::new(speed_curve(1));
+    for i in 1..=SPEED_LEVELS {
         upgrades.push(PurchasableUpgrade {
             upgrade: Upgrade::Pipe1Speed(i),
-            price: BigInt::from_f64(cost.floor()).unwrap(),
+            price: curve.price(i),
             description: "Triangle Pipe Speed".to_owned(),
+            curve: Some(curve.clone()),
             requirements: match i {
                 1 => vec![],
                 n => vec![
@@ -178,13 +219,13 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
         });
     }
 
-    for i in 1..=40 {
-        let if64 = i as f64;
-        let cost = 4_000_000f64*3.2f64.powf(if64)*12f64 + 40f64 + 10f64*if64*if64;
+    let curve = Rc::new(speed_curve(4_000_000));
+    for i in 1..=SPEED_LEVELS {
         upgrades.push(PurchasableUpgrade {
             upgrade: Upgrade::Pipe2Speed(i),
-            price: BigInt::from_f64(cost.floor()).unwrap(),
+            price: curve.price(i),
             description: "Square Pipe Speed".to_owned(),
+            curve: Some(curve.clone()),
             requirements: match i {
                 1 => vec![HasPipe::new(2).rc()],
                 n => vec![
@@ -196,13 +237,13 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
         });
     }
 
-    for i in 1..=40 {
-        let if64 = i as f64;
-        let cost = 50_000_000f64*3.2f64.powf(if64)*12f64 + 40f64 + 10f64*if64*if64;
+    let curve = Rc::new(speed_curve(50_000_000));
+    for i in 1..=SPEED_LEVELS {
         upgrades.push(PurchasableUpgrade {
             upgrade: Upgrade::Pipe3Speed(i),
-            price: BigInt::from_f64(cost.floor()).unwrap(),
+            price: curve.price(i),
             description: "Pentagon Pipe Speed".to_owned(),
+            curve: Some(curve.clone()),
             requirements: match i {
                 1 => vec![HasPipe::new(3).rc()],
                 n => vec![
@@ -214,13 +255,13 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
         });
     }
 
-    for i in 1..=40 {
-        let if64 = i as f64;
-        let cost = 200_000_000f64*3.2f64.powf(if64)*12f64 + 40f64 + 10f64*if64*if64;
+    let curve = Rc::new(speed_curve(200_000_000));
+    for i in 1..=SPEED_LEVELS {
         upgrades.push(PurchasableUpgrade {
             upgrade: Upgrade::Pipe4Speed(i),
-            price: BigInt::from_f64(cost.floor()).unwrap(),
+            price: curve.price(i),
             description: "Hexagon Pipe Speed".to_owned(),
+            curve: Some(curve.clone()),
             requirements: match i {
                 1 => vec![HasPipe::new(4).rc()],
                 n => vec![
@@ -232,5 +273,69 @@ const ALL_UPGRADES_MEMO: Lazy<Vec<PurchasableUpgrade>> = Lazy::new(|| {
         });
     }
 
-    return upgrades;
-});
+    for i in 1..=SALE_MULTIPLIER_LEVELS {
+        upgrades.push(PurchasableUpgrade {
+            upgrade: Upgrade::SaleMultiplier(i),
+            price: BigInt::from_u64(1_000_000).unwrap() * BigInt::from_u64(1000).unwrap().pow(i as u32 - 1),
+            description: "All Sales +100%".to_owned(),
+            curve: None,
+            requirements: match i {
+                1 => vec![HasPipe::new(2).rc()],
+                n => vec![HasUpgrade::new(Upgrade::SaleMultiplier(n - 1)).rc()],
+            }
+        });
+    }
+
+    // One per pipe shape and the heptagons the laser makes
+    for sides in 3..=7 {
+        upgrades.push(PurchasableUpgrade {
+            upgrade: Upgrade::SidesMultiplier(sides),
+            price: BigInt::from_u64(50_000).unwrap() * BigInt::from_u64(1000).unwrap().pow(sides as u32 - 3),
+            description: format!("{} Value x5", shape_name(sides)),
+            curve: None,
+            requirements: match sides {
+                7 => vec![HasUpgrade::new(Upgrade::ShapeLaser(1)).rc()],
+                n => vec![HasPipe::new(n as u32 - 2).rc()],
+            }
+        });
+    }
+
+    for (i, c) in shape_colors.iter().enumerate() {
+        upgrades.push(PurchasableUpgrade {
+            upgrade: Upgrade::ColorMultiplier(c.0),
+            price: BigInt::from_u64(50).unwrap() * color_price(i),
+            description: format!("{} Value +50%", c.1),
+            curve: None,
+            requirements: vec![HasUpgrade::new(Upgrade::Pipe1Color(c.0)).rc()],
+        });
+    }
+
+    // Slower shapes stay on the playfield longer, for clicking and dragging
+    for i in 1..=ANTI_GRAVITY_LEVELS {
+        upgrades.push(PurchasableUpgrade {
+            upgrade: Upgrade::AntiGravity(i),
+            price: BigInt::from_u64(20_000).unwrap() * BigInt::from_u64(1000).unwrap().pow(i as u32 - 1),
+            description: format!("Anti-Gravity Field {}", i),
+            curve: None,
+            requirements: match i {
+                1 => vec![HasPipe::new(2).rc()],
+                n => vec![HasUpgrade::new(Upgrade::AntiGravity(n - 1)).rc()],
+            }
+        });
+    }
+
+    for i in 1..=BUMPER_LEVELS {
+        upgrades.push(PurchasableUpgrade {
+            upgrade: Upgrade::Bumpers(i),
+            price: BigInt::from_u64(25_000).unwrap() * BigInt::from_u64(100).unwrap().pow(i as u32 - 1),
+            description: format!("Bumpers and Ramps {}", i),
+            curve: None,
+            requirements: match i {
+                1 => vec![HasPipe::new(2).rc()],
+                n => vec![HasUpgrade::new(Upgrade::Bumpers(n - 1)).rc()],
+            }
+        });
+    }
+
+    upgrades
+}
@@ -0,0 +1,247 @@
+use super::{state::{GameState, Shape}, upgrades::Upgrade, world::{WORLD_HEIGHT, WORLD_WIDTH}};
+
+// Most bumpers and ramps at once with every `Bumpers` upgrade. Each level
+// allows an equal share, placing another once they're used up removes the
+// oldest.
+pub const MAX_PLACED_SEGMENTS: usize = 8;
+pub const BUMPER_LEVELS: u64 = 4;
+// Right clicks shorter than this place a bumper, longer ones a ramp
+const MIN_RAMP_LENGTH: f64 = 20f64;
+const MAX_RAMP_LENGTH: f64 = 400f64;
+const BUMPER_LENGTH: f64 = 60f64;
+// How close a right click has to be to a placed segment to remove it
+const REMOVE_DISTANCE: f64 = 10f64;
+// Shapes touching a segment slower than this are resting on it, and get sold
+// after resting for `MAX_RESTING` milliseconds so nothing stays stuck
+const RESTING_SPEED: f64 = 30f64;
+pub const MAX_RESTING: u64 = 5000;
+// Shapes this close to a segment still count as touching it, a resting shape
+// bobs a little off it between steps
+const CONTACT_GAP: f64 = 2f64;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SegmentKind {
+    // The playfield edges, always there
+    Wall,
+    // Bounces shapes back almost as hard as they hit
+    Bumper,
+    // Barely bounces, shapes slide along it
+    Ramp,
+}
+
+// A line shapes bounce off. The renderer draws the same segments the physics
+// collides with.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+}
+
+// Share of the speed into the segment that is kept, bouncing back out. Never
+// above 1, or shapes would bounce higher and higher.
+fn restitution(kind: SegmentKind) -> f64 {
+    match kind {
+        SegmentKind::Wall => 0.5,
+        SegmentKind::Bumper => 0.9,
+        SegmentKind::Ramp => 0.1,
+    }
+}
+
+// The sides of the world. They reach past the bottom edge so shapes stay
+// between them until they are sold.
+const WALLS: [Segment; 2] = [
+    Segment { kind: SegmentKind::Wall, start: (0f64, -200f64), end: (0f64, WORLD_HEIGHT as f64 + 200f64) },
+    Segment {
+        kind: SegmentKind::Wall,
+        start: (WORLD_WIDTH as f64, -200f64),
+        end: (WORLD_WIDTH as f64, WORLD_HEIGHT as f64 + 200f64),
+    },
+];
+
+// Walls first, then the placed bumpers and ramps oldest first
+pub fn segments(state: &GameState) -> Vec<Segment> {
+    WALLS.iter().chain(&state.placed_segments).copied().collect()
+}
+
+pub fn collide_tick(state: &mut GameState, dt: u64) {
+    let segments = segments(state);
+    for shape in &mut state.shapes {
+        shape.sliding = false;
+        if shape.held {
+            shape.resting = 0;
+            continue;
+        }
+        for segment in &segments {
+            shape.sliding |= collide(shape, segment);
+        }
+        match shape.sliding && shape.vel_x.hypot(shape.vel_y) < RESTING_SPEED {
+            true => shape.resting += dt,
+            false => shape.resting = 0,
+        }
+    }
+}
+
+// Treats the shape as a circle around its farthest corner, pushes it out of
+// the segment and reflects the speed it had into it. True if they touched.
+fn collide(shape: &mut Shape, segment: &Segment) -> bool {
+    let radius = shape.vertices.iter().map(|(x, y)| x.hypot(*y)).fold(0f64, f64::max);
+    let (closest_x, closest_y) = closest_point(segment, shape.x, shape.y);
+    let (dx, dy) = (shape.x - closest_x, shape.y - closest_y);
+    let distance = dx.hypot(dy);
+    if distance >= radius + CONTACT_GAP || distance == 0f64 {
+        return false;
+    }
+    if distance >= radius {
+        return true;
+    }
+    let (nx, ny) = (dx / distance, dy / distance);
+    shape.x += nx * (radius - distance);
+    shape.y += ny * (radius - distance);
+    let into = shape.vel_x * nx + shape.vel_y * ny;
+    if into < 0f64 {
+        let bounce = (1f64 + restitution(segment.kind)) * into;
+        shape.vel_x -= bounce * nx;
+        shape.vel_y -= bounce * ny;
+    }
+    true
+}
+
+fn closest_point(segment: &Segment, x: f64, y: f64) -> (f64, f64) {
+    let ((x1, y1), (x2, y2)) = (segment.start, segment.end);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0f64 {
+        return (x1, y1);
+    }
+    let t = (((x - x1) * dx + (y - y1) * dy) / length_squared).clamp(0f64, 1f64);
+    (x1 + t * dx, y1 + t * dy)
+}
+
+// How many bumpers and ramps the upgrades allow
+pub fn segment_slots(state: &GameState) -> usize {
+    let level = state.upgrades.iter()
+        .filter_map(|u| match u {
+            Upgrade::Bumpers(level) => Some(*level),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        .min(BUMPER_LEVELS);
+    level as usize * MAX_PLACED_SEGMENTS / BUMPER_LEVELS as usize
+}
+
+// Right button pressed on the playfield. Ignored until there is something to
+// place or remove.
+pub fn start_segment(state: &mut GameState, x: f64, y: f64) {
+    if segment_slots(state) == 0 && state.placed_segments.is_empty() {
+        return;
+    }
+    state.segment_start = Some((x, y));
+}
+
+// Right button released. A short click removes the bumper or ramp under it or
+// places a bumper, a drag places a ramp from where it started.
+pub fn finish_segment(state: &mut GameState, x: f64, y: f64) {
+    let (start_x, start_y) = match state.segment_start.take() {
+        Some(start) => start,
+        None => return,
+    };
+    let length = (x - start_x).hypot(y - start_y);
+    let segment = if length < MIN_RAMP_LENGTH {
+        let near = state.placed_segments.iter().position(|s| {
+            let (closest_x, closest_y) = closest_point(s, x, y);
+            (x - closest_x).hypot(y - closest_y) < REMOVE_DISTANCE
+        });
+        if let Some(i) = near {
+            state.placed_segments.remove(i);
+            return;
+        }
+        Segment {
+            kind: SegmentKind::Bumper,
+            start: (x - BUMPER_LENGTH / 2f64, y),
+            end: (x + BUMPER_LENGTH / 2f64, y),
+        }
+    } else {
+        let scale = (MAX_RAMP_LENGTH / length).min(1f64);
+        Segment {
+            kind: SegmentKind::Ramp,
+            start: (start_x, start_y),
+            end: (start_x + (x - start_x) * scale, start_y + (y - start_y) * scale),
+        }
+    };
+    let slots = segment_slots(state);
+    if slots == 0 {
+        return;
+    }
+    while state.placed_segments.len() >= slots {
+        state.placed_segments.remove(0);
+    }
+    state.placed_segments.push(segment);
+}
+
+#[cfg(test)]
+mod tests {
+    use raylib::prelude::Color;
+
+    use super::*;
+    use crate::game_logic::{score::Score, shape::{regular_shape, shape_tick}, state::seeded_state, tick::STEP, upgrades::Upgrade};
+
+    const CENTER: (f64, f64) = (WORLD_WIDTH as f64 / 2f64, WORLD_HEIGHT as f64 / 2f64);
+
+    // Drops a square onto a flat segment under it and returns the
+    // milliseconds until it was sold, if it was within a minute
+    fn time_to_leave(kind: SegmentKind) -> Option<u64> {
+        let mut state = seeded_state(1);
+        let (x, y) = CENTER;
+        state.placed_segments.push(Segment { kind, start: (x - 30f64, y), end: (x + 30f64, y) });
+        state.shapes = vec![regular_shape(4, Color::GRAY, x, y - 100f64)];
+        for step in 1..=60_000 / STEP {
+            shape_tick(&mut state, STEP);
+            if state.shapes.is_empty() {
+                assert!(state.score > Score::zero());
+                return Some(step * STEP);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn shapes_leave_a_bumper() {
+        assert!(time_to_leave(SegmentKind::Bumper).is_some());
+    }
+
+    #[test]
+    fn shapes_leave_a_flat_ramp() {
+        assert!(time_to_leave(SegmentKind::Ramp).is_some());
+    }
+
+    #[test]
+    fn bounces_never_speed_shapes_up() {
+        let (x, y) = CENTER;
+        for kind in [SegmentKind::Wall, SegmentKind::Bumper, SegmentKind::Ramp] {
+            let segment = Segment { kind, start: (x - 30f64, y), end: (x + 30f64, y) };
+            let mut shape = Shape { vel_x: 50f64, vel_y: 500f64, ..regular_shape(4, Color::GRAY, x, y - 20f64) };
+            assert!(collide(&mut shape, &segment));
+            assert!(shape.vel_y < 0f64);
+            assert!(shape.vel_x.hypot(shape.vel_y) <= 50f64.hypot(500f64));
+        }
+    }
+
+    #[test]
+    fn placing_needs_the_upgrade() {
+        let mut state = seeded_state(1);
+        let (x, y) = CENTER;
+        start_segment(&mut state, x, y);
+        finish_segment(&mut state, x, y);
+        assert!(state.placed_segments.is_empty());
+
+        state.upgrades.push(Upgrade::Bumpers(1));
+        for i in 0..3 {
+            start_segment(&mut state, x + 100f64 * i as f64, y);
+            finish_segment(&mut state, x + 100f64 * i as f64, y);
+        }
+        assert_eq!(state.placed_segments.len(), segment_slots(&state));
+        assert_eq!(state.placed_segments[0].start.0, x + 100f64 - BUMPER_LENGTH / 2f64);
+    }
+}
@@ -0,0 +1,4 @@
+// Size of the playfield in logical world units. Shapes, pipes and lasers all
+// live in this space, the renderer scales it to whatever the window is.
+pub const WORLD_WIDTH: i32 = 640;
+pub const WORLD_HEIGHT: i32 = 720;
@@ -0,0 +1,41 @@
+use std::{fs, path::PathBuf, time::SystemTime};
+
+// Seconds between checks, stat-ing every file each frame adds up
+const POLL_INTERVAL: f64 = 0.5;
+
+// Notices when any of a set of files is modified, created or deleted. Used in
+// dev mode to reload the assets and upgrade catalog while the game runs.
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_poll: f64,
+}
+
+impl FileWatcher {
+    pub fn new(files: &[PathBuf]) -> FileWatcher {
+        FileWatcher {
+            files: files.iter().map(|f| (f.clone(), modified(f))).collect(),
+            last_poll: 0f64,
+        }
+    }
+
+    // True once for every batch of changes. `now` is in seconds.
+    pub fn poll(&mut self, now: f64) -> bool {
+        if now - self.last_poll < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = now;
+        let mut changed = false;
+        for (path, last_modified) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,157 @@
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+pub const ALL_LANGUAGES: [Language; 2] = [Language::English, Language::Spanish];
+
+// UI strings that are translated. Upgrade and achievement names are content
+// and stay in English. Strings are plain ASCII since that is all the default
+// raylib font can draw.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Text {
+    Score,
+    Upgrades,
+    Buy,
+    Paused,
+    Achievements,
+    AchievementUnlocked,
+    PressToClose,
+    Settings,
+    FpsCap,
+    Unlimited,
+    VSync,
+    On,
+    Off,
+    Notation,
+    NotationNamed,
+    NotationAbbreviated,
+    NotationScientific,
+    Language,
+    ParticleDensity,
+    Autosave,
+    MasterVolume,
+    SoundVolume,
+    MusicVolume,
+    KeyBindings,
+    PressAKey,
+    Multipliers,
+    AllSales,
+    GoldenBoost,
+    DropBoost,
+    SaleBoost,
+    Worth,
+    Area,
+    LaserCuts,
+    Pipe,
+    DropsPerSecond,
+    IncomeShare,
+}
+
+pub fn language_name(language: Language) -> &'static str {
+    match language {
+        Language::English => "English",
+        Language::Spanish => "Espanol",
+    }
+}
+
+pub fn language_id(language: Language) -> &'static str {
+    match language {
+        Language::English => "en",
+        Language::Spanish => "es",
+    }
+}
+
+pub fn language_from_id(id: &str) -> Option<Language> {
+    ALL_LANGUAGES.iter().find(|l| language_id(**l) == id).copied()
+}
+
+pub fn text(language: Language, text: Text) -> &'static str {
+    match language {
+        Language::English => english(text),
+        Language::Spanish => spanish(text),
+    }
+}
+
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::Score => "Score",
+        Text::Upgrades => "Upgrades",
+        Text::Buy => "Buy",
+        Text::Paused => "Paused",
+        Text::Achievements => "Achievements",
+        Text::AchievementUnlocked => "Achievement unlocked!",
+        Text::PressToClose => "Press to close:",
+        Text::Settings => "Settings",
+        Text::FpsCap => "FPS cap",
+        Text::Unlimited => "Unlimited",
+        Text::VSync => "VSync",
+        Text::On => "On",
+        Text::Off => "Off",
+        Text::Notation => "Number notation",
+        Text::NotationNamed => "Named",
+        Text::NotationAbbreviated => "Abbreviated",
+        Text::NotationScientific => "Scientific",
+        Text::Language => "Language",
+        Text::ParticleDensity => "Particle density",
+        Text::Autosave => "Autosave",
+        Text::MasterVolume => "Master volume",
+        Text::SoundVolume => "Sound volume",
+        Text::MusicVolume => "Music volume",
+        Text::KeyBindings => "Key bindings",
+        Text::PressAKey => "Press a key (Backspace to cancel)",
+        Text::Multipliers => "Multipliers",
+        Text::AllSales => "All sales",
+        Text::GoldenBoost => "Golden boost",
+        Text::DropBoost => "Drops",
+        Text::SaleBoost => "Sales",
+        Text::Worth => "Worth",
+        Text::Area => "Area",
+        Text::LaserCuts => "Laser cuts",
+        Text::Pipe => "Pipe",
+        Text::DropsPerSecond => "Drops/s",
+        Text::IncomeShare => "Income share",
+    }
+}
+
+fn spanish(text: Text) -> &'static str {
+    match text {
+        Text::Score => "Puntos",
+        Text::Upgrades => "Mejoras",
+        Text::Buy => "Comprar",
+        Text::Paused => "Pausa",
+        Text::Achievements => "Logros",
+        Text::AchievementUnlocked => "Logro desbloqueado!",
+        Text::PressToClose => "Pulsa para cerrar:",
+        Text::Settings => "Opciones",
+        Text::FpsCap => "Limite de FPS",
+        Text::Unlimited => "Sin limite",
+        Text::VSync => "VSync",
+        Text::On => "Si",
+        Text::Off => "No",
+        Text::Notation => "Notacion numerica",
+        Text::NotationNamed => "Nombres",
+        Text::NotationAbbreviated => "Abreviada",
+        Text::NotationScientific => "Cientifica",
+        Text::Language => "Idioma",
+        Text::ParticleDensity => "Densidad de particulas",
+        Text::Autosave => "Autoguardado",
+        Text::MasterVolume => "Volumen general",
+        Text::SoundVolume => "Volumen de efectos",
+        Text::MusicVolume => "Volumen de musica",
+        Text::KeyBindings => "Controles",
+        Text::PressAKey => "Pulsa una tecla (Retroceso para cancelar)",
+        Text::Multipliers => "Multiplicadores",
+        Text::AllSales => "Todas las ventas",
+        Text::GoldenBoost => "Bono dorado",
+        Text::DropBoost => "Caidas",
+        Text::SaleBoost => "Ventas",
+        Text::Worth => "Valor",
+        Text::Area => "Area",
+        Text::LaserCuts => "Cortes laser",
+        Text::Pipe => "Tubo",
+        Text::DropsPerSecond => "Caidas/s",
+        Text::IncomeShare => "Parte de ingresos",
+    }
+}
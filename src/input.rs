@@ -0,0 +1,152 @@
+use raylib::{RaylibHandle, consts::{GamepadButton, KeyboardKey, MouseButton}};
+
+use crate::{drawing::{pipe_panel_action, pipe_panel_rect, upgrade_at}, game_logic::{action::Action, state::GameState}, layout::Layout};
+
+const GAMEPAD: i32 = 0;
+// Speed levels bought at once by shift clicking one in the shop
+const SHIFT_CLICK_LEVELS: u32 = 10;
+
+pub struct KeyBinding {
+    pub key: KeyboardKey,
+    pub action: Action,
+}
+
+pub struct GamepadBinding {
+    pub button: GamepadButton,
+    pub action: Action,
+}
+
+pub struct InputBindings {
+    pub keys: Vec<KeyBinding>,
+    pub gamepad: Vec<GamepadBinding>,
+}
+
+impl InputBindings {
+    // Replaces every key currently bound to `action` with `key`
+    pub fn rebind_key(&mut self, action: Action, key: KeyboardKey) {
+        self.keys.retain(|b| b.action != action && b.key != key);
+        self.keys.push(KeyBinding { key, action });
+    }
+
+    pub fn keys_for(&self, action: Action) -> Vec<KeyboardKey> {
+        self.keys.iter().filter(|b| b.action == action).map(|b| b.key).collect()
+    }
+}
+
+// Actions the player can rebind from the settings menu, in display order
+pub const REBINDABLE_ACTIONS: [Action; 11] = [
+    Action::SelectPrevious,
+    Action::SelectNext,
+    Action::BuySelected,
+    Action::ScrollShop(-5),
+    Action::ScrollShop(5),
+    Action::Adjust(-1),
+    Action::Adjust(1),
+    Action::TogglePause,
+    Action::ToggleAchievements,
+    Action::ToggleSettings,
+    Action::ToggleFullscreen,
+];
+
+pub fn key_name(key: KeyboardKey) -> String {
+    let name = format!("{:?}", key);
+    name.trim_start_matches("KEY_").replace('_', " ")
+}
+
+pub fn default_bindings() -> InputBindings {
+    InputBindings {
+        keys: vec![
+            KeyBinding { key: KeyboardKey::KEY_UP, action: Action::SelectPrevious },
+            KeyBinding { key: KeyboardKey::KEY_W, action: Action::SelectPrevious },
+            KeyBinding { key: KeyboardKey::KEY_DOWN, action: Action::SelectNext },
+            KeyBinding { key: KeyboardKey::KEY_S, action: Action::SelectNext },
+            KeyBinding { key: KeyboardKey::KEY_ENTER, action: Action::BuySelected },
+            KeyBinding { key: KeyboardKey::KEY_SPACE, action: Action::BuySelected },
+            KeyBinding { key: KeyboardKey::KEY_PAGE_UP, action: Action::ScrollShop(-5) },
+            KeyBinding { key: KeyboardKey::KEY_PAGE_DOWN, action: Action::ScrollShop(5) },
+            KeyBinding { key: KeyboardKey::KEY_P, action: Action::TogglePause },
+            KeyBinding { key: KeyboardKey::KEY_A, action: Action::ToggleAchievements },
+            KeyBinding { key: KeyboardKey::KEY_F11, action: Action::ToggleFullscreen },
+            KeyBinding { key: KeyboardKey::KEY_O, action: Action::ToggleSettings },
+            KeyBinding { key: KeyboardKey::KEY_LEFT, action: Action::Adjust(-1) },
+            KeyBinding { key: KeyboardKey::KEY_RIGHT, action: Action::Adjust(1) },
+        ],
+        gamepad: vec![
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP, action: Action::SelectPrevious },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN, action: Action::SelectNext },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN, action: Action::BuySelected },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1, action: Action::ScrollShop(-5) },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1, action: Action::ScrollShop(5) },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT, action: Action::TogglePause },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP, action: Action::ToggleAchievements },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT, action: Action::ToggleSettings },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT, action: Action::Adjust(-1) },
+            GamepadBinding { button: GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT, action: Action::Adjust(1) },
+        ],
+    }
+}
+
+// Translates this frame's mouse, keyboard and gamepad input into actions
+// `menu_open` is whether the settings menu is showing. It and the achievements
+// list cover the playfield, so clicks there don't reach it.
+pub fn poll_actions(rl: &RaylibHandle, bindings: &InputBindings, layout: &Layout, menu_open: bool, state: &mut GameState) {
+    let (x, y) = (rl.get_mouse_x(), rl.get_mouse_y());
+    let (left, top, width, height) = layout.playfield;
+    let covered = menu_open || state.show_achievements;
+    // The pipe panel covers part of the playfield and takes its clicks
+    let on_panel = match pipe_panel_rect(state, layout) {
+        Some((panel_x, panel_y, panel_width, panel_height)) => {
+            x >= panel_x && x < panel_x + panel_width && y >= panel_y && y < panel_y + panel_height
+        },
+        None => false,
+    };
+    let on_playfield = x >= left && x < left + width && y >= top && y < top + height && !on_panel && !covered;
+    let (world_x, world_y) = layout.screen_to_world(x, y);
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) && on_playfield {
+        state.actions.push_back(Action::Grab(world_x, world_y));
+    }
+    if rl.is_mouse_button_pressed(MouseButton::MOUSE_RIGHT_BUTTON) && on_playfield {
+        state.actions.push_back(Action::StartSegment(world_x, world_y));
+    }
+    if rl.is_mouse_button_released(MouseButton::MOUSE_RIGHT_BUTTON) && state.segment_start.is_some() {
+        state.actions.push_back(Action::FinishSegment(world_x, world_y));
+    }
+    // Only while something is held, so replays don't fill up with mouse moves
+    if let Some(drag) = state.drag {
+        if drag.target != (world_x as f64, world_y as f64) {
+            state.actions.push_back(Action::Drag(world_x, world_y));
+        }
+    }
+    if rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
+        if let Some(index) = upgrade_at(state, layout, x, y) {
+            let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            state.actions.push_back(match shift {
+                true => Action::BuyLevels(index, SHIFT_CLICK_LEVELS),
+                false => Action::Buy(index),
+            });
+        }
+        if let Some(action) = pipe_panel_action(state, layout, x, y).filter(|_| !covered) {
+            state.actions.push_back(action);
+        }
+        // A drag ends wherever the button comes up, even under a menu
+        if on_playfield || state.drag.is_some() {
+            state.actions.push_back(Action::Click(world_x, world_y));
+        }
+    }
+    let wheel = rl.get_mouse_wheel_move();
+    if wheel != 0f32 {
+        state.actions.push_back(Action::ScrollShop(-wheel.signum() as i32));
+    }
+    for binding in &bindings.keys {
+        if rl.is_key_pressed(binding.key) {
+            state.actions.push_back(binding.action);
+        }
+    }
+    if rl.is_gamepad_available(GAMEPAD) {
+        for binding in &bindings.gamepad {
+            if rl.is_gamepad_button_pressed(GAMEPAD, binding.button) {
+                state.actions.push_back(binding.action);
+            }
+        }
+    }
+}
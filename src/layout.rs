@@ -0,0 +1,140 @@
+use raylib::prelude::{Camera2D, Vector2};
+
+use crate::game_logic::world::{WORLD_HEIGHT, WORLD_WIDTH};
+
+pub const DEFAULT_WIDTH: i32 = 1280;
+pub const DEFAULT_HEIGHT: i32 = 720;
+
+// Unscaled sizes of the upgrade panel, multiplied by `Layout::ui_scale`
+const PANEL_MIN_WIDTH: i32 = 480;
+const PANEL_PADDING: i32 = 32;
+const SHOP_TOP: i32 = 32 + 24 + 12 + 32 + 12;
+const SHOP_ROW_HEIGHT: i32 = 36 + 12;
+const BUY_BUTTON_WIDTH: i32 = 50;
+const BUY_BUTTON_HEIGHT: i32 = 36;
+// Unscaled sizes of the pipe panel, which sits on the right of the playfield
+// below the pipes
+const PIPE_PANEL_WIDTH: i32 = 320;
+const PIPE_PANEL_TOP: i32 = 240;
+const PIPE_PANEL_PADDING: i32 = 12;
+const PIPE_PANEL_HEADER: i32 = 88;
+const PIPE_PANEL_ROW_HEIGHT: i32 = 28;
+const PIPE_PANEL_BUTTON: i32 = 20;
+
+// Where everything goes on screen for the current window size. Rebuilt every
+// frame and shared by drawing and hit testing so the two can never disagree.
+//
+// raylib reports the window size and mouse position in the same screen
+// coordinates it draws in and scales the framebuffer for high DPI displays
+// itself, so nothing here needs to know about the DPI.
+pub struct Layout {
+    pub height: i32,
+    pub ui_scale: f32,
+    // Screen rectangle holding the playfield, left of the panel
+    pub playfield: (i32, i32, i32, i32),
+    pub panel: (i32, i32, i32, i32),
+    // Maps world coordinates into the playfield, letterboxed to keep the aspect
+    pub camera: Camera2D,
+}
+
+impl Layout {
+    pub fn new(width: i32, height: i32) -> Layout {
+        let ui_scale = (height as f32 / DEFAULT_HEIGHT as f32).clamp(0.75, 2f32);
+        let panel_width = (width / 2)
+            .max((PANEL_MIN_WIDTH as f32 * ui_scale) as i32)
+            .min(width);
+        let playfield = (0, 0, width - panel_width, height);
+        let zoom = (playfield.2 as f32 / WORLD_WIDTH as f32)
+            .min(playfield.3 as f32 / WORLD_HEIGHT as f32)
+            .max(0.01);
+        let camera = Camera2D {
+            offset: Vector2::new(
+                playfield.0 as f32 + (playfield.2 as f32 - WORLD_WIDTH as f32 * zoom) / 2f32,
+                playfield.1 as f32 + (playfield.3 as f32 - WORLD_HEIGHT as f32 * zoom) / 2f32,
+            ),
+            target: Vector2::new(0f32, 0f32),
+            rotation: 0f32,
+            zoom,
+        };
+        Layout {
+            height,
+            ui_scale,
+            playfield,
+            panel: (width - panel_width, 0, panel_width, height),
+            camera,
+        }
+    }
+
+    // Scales a size from the 1280x720 design to the current window
+    pub fn px(&self, size: i32) -> i32 {
+        (size as f32 * self.ui_scale).round() as i32
+    }
+
+    pub fn shop_top(&self) -> i32 {
+        self.px(SHOP_TOP)
+    }
+
+    // Number of upgrades that fit in the panel at once
+    pub fn shop_rows(&self) -> usize {
+        ((self.height - self.shop_top()) / self.px(SHOP_ROW_HEIGHT)).max(1) as usize
+    }
+
+    pub fn world_to_screen(&self, x: f64, y: f64) -> (i32, i32) {
+        (
+            (x as f32 * self.camera.zoom + self.camera.offset.x).round() as i32,
+            (y as f32 * self.camera.zoom + self.camera.offset.y).round() as i32,
+        )
+    }
+
+    // Inverse of `world_to_screen`, for clicks on the playfield
+    pub fn screen_to_world(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((x as f32 - self.camera.offset.x) / self.camera.zoom).round() as i32,
+            ((y as f32 - self.camera.offset.y) / self.camera.zoom).round() as i32,
+        )
+    }
+
+    // Screen rectangle of the buy button for the given visible shop row
+    pub fn upgrade_button_rect(&self, row: usize) -> (i32, i32, i32, i32) {
+        (
+            self.panel.0 + self.panel.2 - self.px(BUY_BUTTON_WIDTH + PANEL_PADDING),
+            self.shop_top() + row as i32 * self.px(SHOP_ROW_HEIGHT),
+            self.px(BUY_BUTTON_WIDTH),
+            self.px(BUY_BUTTON_HEIGHT),
+        )
+    }
+
+    // Screen rectangle of the pipe panel with `rows` colors
+    pub fn pipe_panel_rect(&self, rows: usize) -> (i32, i32, i32, i32) {
+        let width = self.px(PIPE_PANEL_WIDTH);
+        (
+            self.playfield.0 + self.playfield.2 - self.px(16) - width,
+            self.playfield.1 + self.px(PIPE_PANEL_TOP),
+            width,
+            self.px(PIPE_PANEL_HEADER + PIPE_PANEL_PADDING) + rows as i32 * self.px(PIPE_PANEL_ROW_HEIGHT),
+        )
+    }
+
+    pub fn pipe_panel_close_rect(&self) -> (i32, i32, i32, i32) {
+        let (x, y, width, _) = self.pipe_panel_rect(0);
+        let size = self.px(PIPE_PANEL_BUTTON);
+        (x + width - self.px(PIPE_PANEL_PADDING) - size, y + self.px(PIPE_PANEL_PADDING), size, size)
+    }
+
+    // The color row's on/off box, at its left end
+    pub fn pipe_panel_toggle_rect(&self, row: usize) -> (i32, i32, i32, i32) {
+        let (x, y, _, _) = self.pipe_panel_rect(0);
+        let size = self.px(PIPE_PANEL_BUTTON);
+        let row_y = y + self.px(PIPE_PANEL_HEADER) + row as i32 * self.px(PIPE_PANEL_ROW_HEIGHT);
+        (x + self.px(PIPE_PANEL_PADDING), row_y, size, size)
+    }
+
+    // The color row's weight buttons, `delta` -1 for the minus one left of
+    // the weight and 1 for the plus one right of it
+    pub fn pipe_panel_weight_rect(&self, row: usize, delta: i32) -> (i32, i32, i32, i32) {
+        let (x, _, _, _) = self.pipe_panel_rect(0);
+        let (_, row_y, size, _) = self.pipe_panel_toggle_rect(row);
+        let offset = if delta < 0 { 150 } else { 150 + PIPE_PANEL_BUTTON + 24 };
+        (x + self.px(offset), row_y, size, size)
+    }
+}
@@ -0,0 +1,5 @@
+// Game rules, saves and replays without any windowing, shared by the game and
+// the tools in src/bin
+pub mod game_logic;
+pub mod replay;
+pub mod save;
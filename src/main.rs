@@ -1,28 +1,235 @@
 use drawing::draw;
-use game_logic::{state::initial_state, tick};
-use num::{BigInt, FromPrimitive};
-use raylib::{prelude::*};
+use match_game::{game_logic, replay, save};
+use game_logic::{action::Action, events::GameEvent, state::{GameState, initial_state}, tick};
+use layout::{DEFAULT_HEIGHT, DEFAULT_WIDTH, Layout};
+use raylib::{RaylibHandle, core::window::{get_current_monitor, get_monitor_height, get_monitor_width}};
 
 mod assets;
-mod game_logic;
+mod audio;
+#[cfg(feature = "dev")]
+mod console;
 mod drawing;
+mod hot_reload;
+mod input;
+mod layout;
+mod particles;
+mod pipe_skins;
+mod settings;
+mod i18n;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned();
+    // Reloads the assets and upgrade catalog whenever they change on disk
+    let dev_mode = args.iter().any(|arg| arg == "--dev");
+    // Records the session to this replay file, written on exit
+    let record_path = arg_value("--record");
+    // Plays a replay file instead of the save, nothing is saved
+    let replay = arg_value("--replay").map(|path| replay::read_replay(&path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }));
     let (mut rl, thread) = raylib::init()
-        .size(1280, 720)
+        .size(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+        .resizable()
         .title("Geometric Plumber")
         .build();
+    rl.set_window_min_size(640, 360);
 
-    rl.set_target_fps(60);
-    let loaded_assets = assets::load_assets(&mut rl, &thread);
+    let mut settings = settings::load_settings(settings::SETTINGS_PATH);
+    let mut settings_menu = settings::closed_settings_menu();
+    settings::apply_settings(&mut rl, &settings);
+    let mut loaded_assets = assets::load_assets(&mut rl, &thread);
+    let mut upgrade_overrides = load_upgrade_catalog(&loaded_assets);
+    let physics = load_physics(&loaded_assets);
+    let lasers = load_lasers(&loaded_assets);
+    let mut watcher = dev_mode.then(|| hot_reload::FileWatcher::new(&loaded_assets.files));
+    let audio_backend: Box<dyn audio::AudioBackend> = match audio::RaylibAudioBackend::new(&thread, &loaded_assets) {
+        Some(backend) => Box::new(backend),
+        None => {
+            eprintln!("No audio device available, continuing without sound");
+            Box::new(audio::NullAudio)
+        }
+    };
+    let mut audio = audio::AudioSystem::new(audio_backend);
+    let mut particles = particles::Particles::new();
+    #[cfg(feature = "dev")]
+    let mut console = console::closed_console();
     let mut state = initial_state();
+    let mut player = None;
+    // Off when the save failed to load, so it isn't overwritten
+    let mut saving = replay.is_none();
+    match &replay {
+        Some(replay) => {
+            state = replay::replay_start_state(replay).unwrap_or_else(|e| {
+                eprintln!("Could not start the replay: {}", e);
+                std::process::exit(1);
+            });
+            player = Some(replay::ReplayPlayer::new(replay));
+        },
+        None => if let Err(e) = save::load_game(&mut state, save::SAVE_PATH) {
+            eprintln!("Could not load save, starting fresh: {}", e);
+            state = initial_state();
+            saving = false;
+            match save::back_up_save(save::SAVE_PATH, save::BAD_SAVE_PATH) {
+                Ok(()) => eprintln!("Kept the old save as {}, this session won't be saved", save::BAD_SAVE_PATH),
+                Err(e) => eprintln!("{}, this session won't be saved", e),
+            }
+        },
+    }
+    // A replay brings its own
+    if replay.is_none() {
+        state.physics = physics;
+        state.lasers = lasers;
+    }
+    let mut replay_finished = false;
+    let mut recorder = record_path.as_ref().map(|_| replay::ReplayRecorder::start(&state, &upgrade_overrides));
 
+    let mut last_save = rl.get_time();
     while !rl.window_should_close() {
-        if rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
-            state.mouse_click_x = rl.get_mouse_x();
-            state.mouse_click_y = rl.get_mouse_y();
+        if let Some(watcher) = &mut watcher {
+            if watcher.poll(rl.get_time()) {
+                eprintln!("Reloading assets, upgrades and physics");
+                loaded_assets = assets::load_assets(&mut rl, &thread);
+                upgrade_overrides = load_upgrade_catalog(&loaded_assets);
+                if let Some(recorder) = &mut recorder {
+                    recorder.record_data("upgrades", &upgrade_overrides);
+                }
+                state.physics = load_physics(&loaded_assets);
+                state.lasers = load_lasers(&loaded_assets);
+                *watcher = hot_reload::FileWatcher::new(&loaded_assets.files);
+            }
+        }
+        let mut layout = Layout::new(rl.get_screen_width(), rl.get_screen_height());
+        input::poll_actions(&rl, &settings.bindings, &layout, settings_menu.open, &mut state);
+        #[cfg(feature = "dev")]
+        if let Some(line) = console::console_tick(&mut rl, &mut console, &mut state) {
+            if let Some(recorder) = &mut recorder {
+                recorder.record_command(&line);
+            }
+        }
+        if settings::settings_menu_tick(&mut rl, &mut settings_menu, &mut settings, &layout, &mut state) {
+            settings::apply_settings(&mut rl, &settings);
+            if let Err(e) = settings::save_settings(&settings, settings::SETTINGS_PATH) {
+                eprintln!("{}", e);
+            }
+        }
+        if state.actions.contains(&Action::ToggleFullscreen) {
+            // Only ticks drain the queue, and a short frame may run none
+            state.actions.retain(|action| *action != Action::ToggleFullscreen);
+            toggle_fullscreen(&mut rl);
+            layout = Layout::new(rl.get_screen_width(), rl.get_screen_height());
+        }
+        let dt = tick::elapsed(&mut state);
+        // Recorded actions and shop size replace the live ones
+        if player.is_some() {
+            state.actions.clear();
+        } else {
+            state.shop_rows = layout.shop_rows();
+        }
+        // Each tick clears the events, keep them all for the frame
+        let mut events = vec![];
+        for _ in 0..tick::steps(&mut state, dt) {
+            match &mut player {
+                Some(player) => match player.next_tick(&mut state) {
+                    Some(dt) => tick::tick(&mut state, dt),
+                    None if !replay_finished => {
+                        if let Some(replay) = &replay {
+                            report_replay_result(replay, &state);
+                        }
+                        replay_finished = true;
+                    },
+                    None => {},
+                },
+                None => {
+                    if let Some(recorder) = &mut recorder {
+                        recorder.record(&state, tick::STEP);
+                    }
+                    tick::tick(&mut state, tick::STEP);
+                },
+            }
+            events.append(&mut state.events);
+        }
+        state.events = events;
+        audio.update(&state, &settings, rl.get_time());
+        particles.update(&state, &settings, &layout, rl.get_frame_time());
+        let mut needs_save = false;
+        for event in &state.events {
+            if let GameEvent::AchievementUnlocked(_) = event {
+                needs_save = true;
+            }
+        }
+        let autosave_due = settings.autosave_interval > 0 
+            && rl.get_time() - last_save >= settings.autosave_interval as f64;
+        if (needs_save || autosave_due) && saving {
+            write_save(&state);
+            last_save = rl.get_time();
+        }
+        let mut d = rl.begin_drawing(&thread);
+        draw(&mut d, &state, &loaded_assets, &layout, &settings, &settings_menu, &particles);
+        #[cfg(feature = "dev")]
+        console::draw_console(&mut d, &console, &layout);
+    }
+    if saving {
+        write_save(&state);
+    }
+    if let (Some(recorder), Some(path)) = (recorder, record_path) {
+        if let Err(e) = replay::write_replay(&recorder.finish(&state), &path) {
+            eprintln!("{}", e);
         }
-        tick::tick(&mut state);
-        draw(&mut rl, &thread, &state, &loaded_assets);
     }
-}
\ No newline at end of file
+}
+
+fn report_replay_result(replay: &replay::Replay, state: &GameState) {
+    let hash = replay::state_hash(state);
+    match replay.hash {
+        Some(expected) if expected == hash => println!("Replay finished, state hash {:016x} matches", hash),
+        Some(expected) => println!("Replay finished, state hash {:016x} does not match {:016x}", hash, expected),
+        None => println!("Replay finished, state hash {:016x}", hash),
+    }
+}
+
+// The contents of a data asset, empty when it is missing or can't be read
+fn data_text(loaded_assets: &assets::LoadedAssets, name: &str) -> String {
+    match loaded_assets.data.get(name) {
+        Some(source) => assets::read_data(source).unwrap_or_else(|e| {
+            eprintln!("Failed to read {} {}: {}", name, source, e);
+            String::new()
+        }),
+        None => String::new(),
+    }
+}
+
+// Returns the overrides it applied, for the replay recorder
+fn load_upgrade_catalog(loaded_assets: &assets::LoadedAssets) -> String {
+    let overrides = data_text(loaded_assets, "upgrades");
+    game_logic::upgrades::load_upgrade_overrides(&overrides);
+    overrides
+}
+
+fn load_physics(loaded_assets: &assets::LoadedAssets) -> game_logic::physics::Physics {
+    game_logic::physics::load_physics(&data_text(loaded_assets, "physics"))
+}
+
+fn load_lasers(loaded_assets: &assets::LoadedAssets) -> Vec<(u64, game_logic::laser::Laser)> {
+    game_logic::laser::load_lasers(&data_text(loaded_assets, "lasers"))
+}
+
+fn write_save(state: &GameState) {
+    if let Err(e) = save::save_game(state, save::SAVE_PATH) {
+        eprintln!("{}", e);
+    }
+}
+
+// Fullscreen keeps the window resolution in raylib, so switch to the monitor
+// resolution first and restore the default size when leaving it
+fn toggle_fullscreen(rl: &mut RaylibHandle) {
+    if rl.is_window_fullscreen() {
+        rl.toggle_fullscreen();
+        rl.set_window_size(DEFAULT_WIDTH, DEFAULT_HEIGHT);
+    } else {
+        let monitor = get_current_monitor();
+        rl.set_window_size(get_monitor_width(monitor), get_monitor_height(monitor));
+        rl.toggle_fullscreen();
+    }
+}
@@ -0,0 +1,228 @@
+use rand::Rng;
+use raylib::prelude::{Color, RaylibDraw, Vector2};
+
+use crate::{
+    drawing::format_num,
+    game_logic::{events::GameEvent, state::GameState, world::WORLD_HEIGHT},
+    layout::Layout,
+    settings::Settings,
+};
+
+// Hard cap on live particles, new ones are dropped once the pool is full
+const POOL_SIZE: usize = 512;
+// At full density, at most this many sale labels float at once
+const MAX_SALE_LABELS: usize = 24;
+const SPARKS_PER_CUT: f32 = 8f32;
+const BURST_SIZE: f32 = 24f32;
+const SPARK_GRAVITY: f32 = 600f32;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ParticleKind {
+    SaleLabel,
+    Spark,
+    Burst,
+}
+
+// World particles are drawn with the playfield camera, screen particles on top
+// of the UI
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ParticleSpace {
+    World,
+    Screen,
+}
+
+pub struct Particle {
+    pub kind: ParticleKind,
+    pub space: ParticleSpace,
+    pub x: f32,
+    pub y: f32,
+    pub vel_x: f32,
+    pub vel_y: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    pub size: f32,
+    pub color: Color,
+    // Only used by sale labels. Kept with the slot so reusing it doesn't allocate.
+    pub text: String,
+}
+
+// Fixed pool of particles. Dead slots are remembered in `free` and reused so
+// a busy factory doesn't allocate every frame.
+pub struct Particles {
+    pool: Vec<Particle>,
+    alive: Vec<bool>,
+    free: Vec<usize>,
+    sale_labels: usize,
+}
+
+impl Particles {
+    pub fn new() -> Particles {
+        Particles {
+            pool: (0..POOL_SIZE).map(|_| Particle {
+                kind: ParticleKind::Spark,
+                space: ParticleSpace::World,
+                x: 0f32,
+                y: 0f32,
+                vel_x: 0f32,
+                vel_y: 0f32,
+                age: 0f32,
+                lifetime: 0f32,
+                size: 0f32,
+                color: Color::WHITE,
+                text: String::new(),
+            }).collect(),
+            alive: vec![false; POOL_SIZE],
+            free: (0..POOL_SIZE).rev().collect(),
+            sale_labels: 0,
+        }
+    }
+
+    // Spawns particles for this tick's events and advances the live ones by `dt` seconds
+    pub fn update(&mut self, state: &GameState, settings: &Settings, layout: &Layout, dt: f32) {
+        let density = settings.particle_density;
+        if density > 0f32 {
+            for event in &state.events {
+                match event {
+                    GameEvent::ShapeSold { price, color, x, y, .. }
+                        if self.sale_labels < (MAX_SALE_LABELS as f32 * density) as usize => {
+                        let text = format!("+{}", format_num(&price.to_bigint(), settings.notation, true));
+                        // Shapes are sold below the bottom edge, show the label just above it
+                        let y = (*y as f32).min(WORLD_HEIGHT as f32 - 30f32);
+                        self.spawn_sale_label(*x as f32, y, *color, &text);
+                    },
+                    GameEvent::ShapeCut { x, y, .. } => {
+                        let count = (SPARKS_PER_CUT * density).ceil() as usize;
+                        self.spawn_sparks(*x as f32, *y as f32, count);
+                    },
+                    GameEvent::BoostStarted { x, y, .. } => {
+                        let count = (BURST_SIZE * density).ceil() as usize;
+                        self.spawn_sparks(*x as f32, *y as f32, count);
+                    },
+                    GameEvent::UpgradePurchased { index, .. } => {
+                        let row = index.saturating_sub(state.shop_scroll);
+                        let (x, y, width, height) = layout.upgrade_button_rect(row);
+                        let count = (BURST_SIZE * density).ceil() as usize;
+                        self.spawn_burst((x + width/2) as f32, (y + height/2) as f32, count);
+                    },
+                    _ => {},
+                }
+            }
+        }
+        for i in 0..POOL_SIZE {
+            if !self.alive[i] {
+                continue;
+            }
+            let particle = &mut self.pool[i];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                self.alive[i] = false;
+                self.free.push(i);
+                if particle.kind == ParticleKind::SaleLabel {
+                    self.sale_labels -= 1;
+                }
+                continue;
+            }
+            particle.x += particle.vel_x * dt;
+            particle.y += particle.vel_y * dt;
+            match particle.kind {
+                ParticleKind::Spark => particle.vel_y += SPARK_GRAVITY * dt,
+                ParticleKind::Burst => {
+                    particle.vel_x *= (1f32 - dt * 3f32).max(0f32);
+                    particle.vel_y *= (1f32 - dt * 3f32).max(0f32);
+                },
+                ParticleKind::SaleLabel => {},
+            }
+        }
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw, space: ParticleSpace, layout: &Layout) {
+        for (particle, _) in self.pool.iter().zip(&self.alive).filter(|(p, alive)| **alive && p.space == space) {
+            let alpha = 1f32 - particle.age / particle.lifetime;
+            let color = particle.color.fade(alpha);
+            match particle.kind {
+                ParticleKind::SaleLabel => {
+                    d.draw_text(&particle.text, particle.x as i32, particle.y as i32, particle.size as i32, color);
+                },
+                ParticleKind::Spark => {
+                    let tail = Vector2::new(
+                        particle.x - particle.vel_x * 0.02,
+                        particle.y - particle.vel_y * 0.02
+                    );
+                    d.draw_line_ex(tail, Vector2::new(particle.x, particle.y), particle.size, color);
+                },
+                ParticleKind::Burst => {
+                    let radius = layout.px(particle.size as i32) as f32;
+                    d.draw_circle_v(Vector2::new(particle.x, particle.y), radius, color);
+                },
+            }
+        }
+    }
+
+    fn spawn(&mut self) -> Option<&mut Particle> {
+        let i = self.free.pop()?;
+        self.alive[i] = true;
+        let particle = &mut self.pool[i];
+        particle.age = 0f32;
+        Some(particle)
+    }
+
+    fn spawn_sale_label(&mut self, x: f32, y: f32, color: Color, text: &str) {
+        let particle = match self.spawn() {
+            Some(particle) => particle,
+            None => return,
+        };
+        particle.kind = ParticleKind::SaleLabel;
+        particle.space = ParticleSpace::World;
+        particle.x = x - 20f32;
+        particle.y = y;
+        particle.vel_x = 0f32;
+        particle.vel_y = -40f32;
+        particle.lifetime = 1.2;
+        particle.size = 20f32;
+        // Gray labels would vanish against the background
+        particle.color = if color == Color::GRAY { Color::DARKGRAY } else { color };
+        particle.text.clear();
+        particle.text.push_str(text);
+        self.sale_labels += 1;
+    }
+
+    fn spawn_sparks(&mut self, x: f32, y: f32, count: usize) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..count {
+            let particle = match self.spawn() {
+                Some(particle) => particle,
+                None => return,
+            };
+            particle.kind = ParticleKind::Spark;
+            particle.space = ParticleSpace::World;
+            particle.x = x + rng.gen_range(-20f32..20f32);
+            particle.y = y;
+            particle.vel_x = rng.gen_range(-250f32..250f32);
+            particle.vel_y = rng.gen_range(-300f32..-50f32);
+            particle.lifetime = rng.gen_range(0.2f32..0.5f32);
+            particle.size = 2f32;
+            particle.color = if rng.gen_bool(0.5) { Color::ORANGE } else { Color::YELLOW };
+        }
+    }
+
+    fn spawn_burst(&mut self, x: f32, y: f32, count: usize) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..count {
+            let particle = match self.spawn() {
+                Some(particle) => particle,
+                None => return,
+            };
+            let angle = rng.gen_range(0f32..std::f32::consts::TAU);
+            let speed = rng.gen_range(80f32..260f32);
+            particle.kind = ParticleKind::Burst;
+            particle.space = ParticleSpace::Screen;
+            particle.x = x;
+            particle.y = y;
+            particle.vel_x = angle.cos() * speed;
+            particle.vel_y = angle.sin() * speed;
+            particle.lifetime = rng.gen_range(0.3f32..0.6f32);
+            particle.size = rng.gen_range(2f32..5f32);
+            particle.color = if rng.gen_bool(0.5) { Color::LIME } else { Color::GOLD };
+        }
+    }
+}
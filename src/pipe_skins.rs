@@ -0,0 +1,41 @@
+use raylib::prelude::Color;
+
+// How a pipe looks, picked by the sides of the shapes it drops so every tier
+// is recognisable. `texture` names a region of the atlas, one sprite per tier
+// in a different metal.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct PipeSkin {
+    pub sides: i32,
+    pub texture: &'static str,
+    // Multiplies the sprite, later tiers are darker so the arms of the pipes
+    // behind stay apart
+    pub tint: Color,
+    // Shape badge on the nozzle
+    pub trim: Color,
+}
+
+const PIPE_SKINS: [PipeSkin; 4] = [
+    PipeSkin { sides: 3, texture: "pipe_triangle", tint: Color::new(255, 255, 255, 255), trim: Color::new(190, 120, 80, 255) },
+    PipeSkin { sides: 4, texture: "pipe_square", tint: Color::new(235, 235, 235, 255), trim: Color::new(170, 180, 200, 255) },
+    PipeSkin { sides: 5, texture: "pipe_pentagon", tint: Color::new(215, 215, 215, 255), trim: Color::new(220, 190, 90, 255) },
+    PipeSkin { sides: 6, texture: "pipe_hexagon", tint: Color::new(195, 195, 195, 255), trim: Color::new(110, 190, 170, 255) },
+];
+
+// Any other tier, e.g. pipes added from the console
+const DEFAULT_SKIN: PipeSkin = PipeSkin {
+    sides: 0,
+    texture: "pipe",
+    tint: Color::new(200, 200, 200, 255),
+    trim: Color::new(230, 230, 230, 255),
+};
+
+pub fn pipe_skin(sides: i32) -> &'static PipeSkin {
+    PIPE_SKINS.iter().find(|s| s.sides == sides).unwrap_or(&DEFAULT_SKIN)
+}
+
+// Where the parts of the nozzle are in the pipe sprite, in sprite pixels
+pub const COLLAR: (f32, f32, f32, f32) = (16f32, 110f32, 40f32, 28f32);
+pub const BADGE_CENTER: (f32, f32) = (35f32, 168f32);
+pub const BADGE_RADIUS: f32 = 11f32;
+// Milliseconds the nozzle pulses after a drop
+pub const DROP_PULSE: u64 = 150;
@@ -0,0 +1,416 @@
+use std::{fs, str::FromStr};
+
+use crate::{
+    game_logic::{
+        action::Action,
+        laser::{Laser, lasers_text, load_lasers},
+        physics::{Physics, load_physics, physics_text},
+        state::{GameState, seeded_state},
+        tick::tick,
+        upgrades::load_upgrade_overrides,
+    },
+    save::{load_text, save_text},
+};
+#[cfg(feature = "dev")]
+use crate::game_logic::commands::run_command;
+
+// A replay is a text file holding everything needed to rerun a session:
+//
+//   replay 2
+//   seed 1234
+//   save                   the save file the session started from
+//   score 0
+//   end
+//   data upgrades          assets/upgrades.txt as it was loaded, again after
+//   Pipe2 price = 20000    every hot reload
+//   end
+//   data physics           the physics, again whenever they change
+//   gravity = 90
+//   ...
+//   end
+//   data lasers            the lasers, again whenever they change
+//   1 0 640 513.5 0 7
+//   end
+//   rows 9                 `shop_rows` changed, it decides what BuySelected buys
+//   action BuySelected     queued for the next step
+//   command score add 5    a dev console command, run before the next step.
+//                          Only builds with the dev feature can play these.
+//   step 10 x120           120 ticks of 10 ms
+//   hash 9f3a...           `state_hash` at the end
+//
+// Consecutive identical steps without actions in between share one line.
+
+const VERSION: &str = "2";
+// Data assets a replay can carry, see `ReplayEntry::Data`
+const DATA_NAMES: [&str; 3] = ["upgrades", "physics", "lasers"];
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ReplayEntry {
+    Rows(usize),
+    Action(Action),
+    #[cfg(feature = "dev")]
+    Command(String),
+    // The contents of a data asset, applied the way the game loads it
+    Data { name: String, contents: String },
+    Step { dt: u64, count: u32 },
+}
+
+pub struct Replay {
+    pub seed: u64,
+    pub save: String,
+    pub entries: Vec<ReplayEntry>,
+    pub hash: Option<u64>,
+}
+
+pub struct ReplayRecorder {
+    replay: Replay,
+    rows: usize,
+    physics: Option<Physics>,
+    lasers: Option<Vec<(u64, Laser)>>,
+}
+
+impl ReplayRecorder {
+    // Call right after the state was loaded, before its first tick, with the
+    // upgrade overrides the catalog was built from
+    pub fn start(state: &GameState, upgrades: &str) -> ReplayRecorder {
+        let mut recorder = ReplayRecorder {
+            replay: Replay { seed: state.seed, save: save_text(state), entries: vec![], hash: None },
+            rows: 0,
+            physics: None,
+            lasers: None,
+        };
+        recorder.record_data("upgrades", upgrades);
+        recorder
+    }
+
+    // Call when a data asset was reloaded
+    pub fn record_data(&mut self, name: &str, contents: &str) {
+        // Whole lines, so the block's `end` stays on its own line
+        let (name, mut contents) = (name.to_string(), contents.to_string());
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        self.replay.entries.push(ReplayEntry::Data { name, contents });
+    }
+
+    // Call after a console command changed the state
+    #[cfg(feature = "dev")]
+    pub fn record_command(&mut self, line: &str) {
+        self.replay.entries.push(ReplayEntry::Command(line.to_string()));
+    }
+
+    // Call before every tick with the `dt` it is about to get
+    pub fn record(&mut self, state: &GameState, dt: u64) {
+        if state.shop_rows != self.rows {
+            self.rows = state.shop_rows;
+            self.replay.entries.push(ReplayEntry::Rows(state.shop_rows));
+        }
+        if self.physics != Some(state.physics) {
+            self.physics = Some(state.physics);
+            self.record_data("physics", &physics_text(&state.physics));
+        }
+        if self.lasers.as_ref() != Some(&state.lasers) {
+            self.lasers = Some(state.lasers.clone());
+            self.record_data("lasers", &lasers_text(&state.lasers));
+        }
+        let entries = &mut self.replay.entries;
+        entries.extend(state.actions.iter().map(|a| ReplayEntry::Action(*a)));
+        match entries.last_mut() {
+            Some(ReplayEntry::Step { dt: last_dt, count }) if *last_dt == dt => *count += 1,
+            _ => entries.push(ReplayEntry::Step { dt, count: 1 }),
+        }
+    }
+
+    pub fn finish(mut self, state: &GameState) -> Replay {
+        self.replay.hash = Some(state_hash(state));
+        self.replay
+    }
+}
+
+// Feeds a replay back into a state one tick at a time
+pub struct ReplayPlayer {
+    entries: Vec<ReplayEntry>,
+    position: usize,
+    // Ticks already played of the step entry at `position`
+    played: u32,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: &Replay) -> ReplayPlayer {
+        ReplayPlayer { entries: replay.entries.clone(), position: 0, played: 0 }
+    }
+
+    // Queues the recorded actions for the next tick and returns its `dt`, or
+    // None once the replay is over
+    pub fn next_tick(&mut self, state: &mut GameState) -> Option<u64> {
+        while let Some(entry) = self.entries.get(self.position) {
+            match entry {
+                ReplayEntry::Rows(rows) => state.shop_rows = *rows,
+                ReplayEntry::Action(action) => state.actions.push_back(*action),
+                #[cfg(feature = "dev")]
+                ReplayEntry::Command(line) => {
+                    if let Err(e) = run_command(state, line) {
+                        eprintln!("Replayed command '{}' failed: {}", line, e);
+                    }
+                },
+                ReplayEntry::Data { name, contents } => apply_data(state, name, contents),
+                ReplayEntry::Step { dt, count } => {
+                    self.played += 1;
+                    if self.played >= *count {
+                        self.position += 1;
+                        self.played = 0;
+                    }
+                    return Some(*dt);
+                },
+            }
+            self.position += 1;
+        }
+        None
+    }
+}
+
+fn apply_data(state: &mut GameState, name: &str, contents: &str) {
+    match name {
+        "upgrades" => load_upgrade_overrides(contents),
+        "physics" => state.physics = load_physics(contents),
+        "lasers" => state.lasers = load_lasers(contents),
+        _ => {},
+    }
+}
+
+// The state a replay starts from
+pub fn replay_start_state(replay: &Replay) -> Result<GameState, String> {
+    let mut state = seeded_state(replay.seed);
+    load_text(&mut state, &replay.save)?;
+    Ok(state)
+}
+
+// Plays the whole replay without rendering and returns the final state
+pub fn run_replay(replay: &Replay) -> Result<GameState, String> {
+    let mut state = replay_start_state(replay)?;
+    let mut player = ReplayPlayer::new(replay);
+    while let Some(dt) = player.next_tick(&mut state) {
+        tick(&mut state, dt);
+    }
+    Ok(state)
+}
+
+// FNV-1a over everything the simulation depends on. Stable between runs and
+// builds, unlike the standard library hasher.
+pub fn state_hash(state: &GameState) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+    feed(state.score.to_string().as_bytes());
+    for upgrade in &state.upgrades {
+        feed(upgrade.to_string().as_bytes());
+    }
+    for achievement in &state.achievements {
+        feed(&[*achievement as u8]);
+    }
+    for pipe in &state.pipes {
+        feed(&pipe.sides.to_le_bytes());
+        feed(&pipe.speed.to_le_bytes());
+        feed(&pipe.last_drop.to_le_bytes());
+    }
+    for shape in &state.shapes {
+        feed(&shape.x.to_bits().to_le_bytes());
+        feed(&shape.y.to_bits().to_le_bytes());
+        for (x, y) in &shape.vertices {
+            feed(&x.to_bits().to_le_bytes());
+            feed(&y.to_bits().to_le_bytes());
+        }
+        feed(&[shape.color.r, shape.color.g, shape.color.b, shape.golden as u8, shape.held as u8]);
+    }
+    for boost in &state.boosts {
+        feed(&[boost.kind as u8]);
+        feed(&boost.remaining.to_le_bytes());
+    }
+    feed(&state.next_golden.to_le_bytes());
+    for entry in &state.emission {
+        feed(&entry.pipe.to_le_bytes());
+        feed(&[entry.color.r, entry.color.g, entry.color.b, entry.enabled as u8]);
+        feed(&entry.weight.to_le_bytes());
+    }
+    for segment in &state.placed_segments {
+        feed(&[segment.kind as u8]);
+        for value in [segment.start.0, segment.start.1, segment.end.0, segment.end.1] {
+            feed(&value.to_bits().to_le_bytes());
+        }
+    }
+    feed(&[state.paused as u8, state.cut_mode as u8]);
+    feed(&state.shop_selection.to_le_bytes());
+    hash
+}
+
+pub fn write_replay(replay: &Replay, path: &str) -> Result<(), String> {
+    fs::write(path, replay_text(replay)).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+pub fn replay_text(replay: &Replay) -> String {
+    let mut contents = format!("replay {}\nseed {}\nsave\n{}end\n", VERSION, replay.seed, replay.save);
+    for entry in &replay.entries {
+        contents += &match entry {
+            ReplayEntry::Rows(rows) => format!("rows {}\n", rows),
+            ReplayEntry::Action(action) => format!("action {}\n", action),
+            #[cfg(feature = "dev")]
+            ReplayEntry::Command(line) => format!("command {}\n", line),
+            ReplayEntry::Data { name, contents } => format!("data {}\n{}end\n", name, contents),
+            ReplayEntry::Step { dt, count: 1 } => format!("step {}\n", dt),
+            ReplayEntry::Step { dt, count } => format!("step {} x{}\n", dt, count),
+        };
+    }
+    if let Some(hash) = replay.hash {
+        contents += &format!("hash {:016x}\n", hash);
+    }
+    contents
+}
+
+pub fn read_replay(path: &str) -> Result<Replay, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    parse_replay(&contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn parse_replay(contents: &str) -> Result<Replay, String> {
+    let mut lines = contents.lines();
+    if lines.next().map(|l| l.trim()) != Some(&format!("replay {}", VERSION)) {
+        return Err(format!("Not a version {} replay", VERSION));
+    }
+    let mut replay = Replay { seed: 0, save: String::new(), entries: vec![], hash: None };
+    while let Some(line) = lines.next() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        let bad = || format!("Bad replay line '{}'", line);
+        match key {
+            "seed" => replay.seed = value.parse().map_err(|_| bad())?,
+            "save" => {
+                for line in lines.by_ref().take_while(|l| *l != "end") {
+                    replay.save += line;
+                    replay.save.push('\n');
+                }
+            },
+            "rows" => replay.entries.push(ReplayEntry::Rows(value.parse().map_err(|_| bad())?)),
+            "action" => replay.entries.push(ReplayEntry::Action(Action::from_str(value)?)),
+            #[cfg(feature = "dev")]
+            "command" => replay.entries.push(ReplayEntry::Command(value.to_string())),
+            // Playing on without the command would only end in a hash mismatch
+            #[cfg(not(feature = "dev"))]
+            "command" => return Err(format!("'{}' needs a build with the dev feature", line)),
+            "data" if DATA_NAMES.contains(&value) => {
+                let mut contents = String::new();
+                for line in lines.by_ref().take_while(|l| *l != "end") {
+                    contents += line;
+                    contents.push('\n');
+                }
+                replay.entries.push(ReplayEntry::Data { name: value.to_string(), contents });
+            },
+            "step" => {
+                let (dt, count) = match value.split_once(" x") {
+                    Some((dt, count)) => (dt, count.parse().map_err(|_| bad())?),
+                    None => (value, 1),
+                };
+                replay.entries.push(ReplayEntry::Step { dt: dt.parse().map_err(|_| bad())?, count });
+            },
+            "hash" => replay.hash = Some(u64::from_str_radix(value, 16).map_err(|_| bad())?),
+            "" => {},
+            _ => return Err(bad()),
+        }
+    }
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{score::Score, tick::STEP};
+
+    // Pipe2 for almost nothing and right away
+    const OVERRIDES: &str = "Pipe2 price = 10\nPipe2 requires =\n";
+
+    // Records a short session with actions, a physics change, a new laser and,
+    // in dev builds, a console command, and returns the final state with it
+    fn recorded_session() -> (GameState, Replay) {
+        load_upgrade_overrides(OVERRIDES);
+        let mut state = seeded_state(7);
+        state.score = Score::from(50);
+        let mut recorder = ReplayRecorder::start(&state, OVERRIDES);
+        for step in 0..2000 {
+            match step {
+                1 => state.actions.push_back(Action::BuySelected),
+                300 => state.physics.gravity *= 2f64,
+                600 => state.lasers.push((1, Laser { min_x: 0f64, max_x: 640f64, y: 300f64, angle: 10f64, thickness: 7f64 })),
+                #[cfg(feature = "dev")]
+                900 => {
+                    run_command(&mut state, "score add 1000000").unwrap();
+                    recorder.record_command("score add 1000000");
+                },
+                1200 => state.actions.push_back(Action::ToggleAchievements),
+                _ => {},
+            }
+            recorder.record(&state, STEP);
+            tick(&mut state, STEP);
+        }
+        let replay = recorder.finish(&state);
+        // The replay has to bring back the catalog on its own
+        load_upgrade_overrides("");
+        (state, replay)
+    }
+
+    #[test]
+    fn replays_reproduce_the_recorded_session() {
+        let (state, replay) = recorded_session();
+        assert_eq!(state.pipes.len(), 2, "the overridden Pipe2 wasn't bought");
+        let text = replay_text(&replay);
+        assert!(text.contains("data upgrades\nPipe2 price = 10\n"));
+        assert_eq!(text.matches("data physics\n").count(), 2);
+        assert_eq!(text.matches("data lasers\n").count(), 2);
+        assert!(text.contains("action BuySelected\n"));
+        #[cfg(feature = "dev")]
+        assert!(text.contains("command score add 1000000\n"));
+
+        let parsed = parse_replay(&text).unwrap();
+        assert_eq!(replay_text(&parsed), text);
+        let replayed = run_replay(&parsed).unwrap();
+        assert_eq!(state_hash(&replayed), state_hash(&state));
+        assert_eq!(parsed.hash, Some(state_hash(&state)));
+        assert_eq!(replayed.physics, state.physics);
+        assert_eq!(replayed.lasers, state.lasers);
+    }
+
+    #[test]
+    fn tampered_replays_end_on_a_different_hash() {
+        let (_, replay) = recorded_session();
+        let text = replay_text(&replay);
+        let tampered = [
+            text.replacen("score 50", "score 5000", 1),
+            text.replacen("action BuySelected\n", "", 1),
+            text.replacen("Pipe2 price = 10", "Pipe2 price = 20", 1),
+            text.replacen("hash", "step 10 x100\nhash", 1),
+        ];
+        for contents in tampered {
+            assert_ne!(contents, text);
+            let replay = parse_replay(&contents).unwrap();
+            let replayed = run_replay(&replay).unwrap();
+            assert_ne!(Some(state_hash(&replayed)), replay.hash);
+            load_upgrade_overrides("");
+        }
+    }
+
+    #[test]
+    fn bad_replays_are_rejected() {
+        assert!(parse_replay("replay 1\nseed 7\n").is_err());
+        assert!(parse_replay("replay 2\nseed seven\n").is_err());
+        assert!(parse_replay("replay 2\naction Jump\n").is_err());
+        assert!(parse_replay("replay 2\nstep 10 xmany\n").is_err());
+        assert!(parse_replay("replay 2\nteleport 5\n").is_err());
+    }
+
+    #[cfg(not(feature = "dev"))]
+    #[test]
+    fn commands_need_the_dev_feature() {
+        assert!(parse_replay("replay 2\nseed 7\ncommand score add 5\nstep 10\n").is_err());
+    }
+}
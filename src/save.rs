@@ -0,0 +1,165 @@
+use std::{fs, str::FromStr};
+
+use crate::game_logic::{
+    achievements::{achievement_from_id, achievement_info},
+    colors::{color_from_name, color_name},
+    emission::{ColorEmission, MAX_EMISSION_WEIGHT},
+    laser::CutMode,
+    score::Score,
+    state::GameState,
+    upgrades::Upgrade,
+    walls::{Segment, SegmentKind},
+};
+
+pub const SAVE_PATH: &str = "save.txt";
+// Where a save that failed to load is copied to before starting fresh
+pub const BAD_SAVE_PATH: &str = "save.txt.bad";
+
+pub fn save_game(state: &GameState, path: &str) -> Result<(), String> {
+    fs::write(path, save_text(state)).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+pub fn load_game(state: &mut GameState, path: &str) -> Result<(), String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => load_text(state, &contents),
+        // No save yet, keep the fresh state
+        Err(_) => Ok(()),
+    }
+}
+
+// Copies a save that failed to load out of the way so it can be fixed by hand
+pub fn back_up_save(path: &str, backup: &str) -> Result<(), String> {
+    fs::copy(path, backup).map(|_| ()).map_err(|e| format!("Failed to copy {} to {}: {}", path, backup, e))
+}
+
+// The score, purchased upgrades, achievements, cut mode, pipe color mixes and
+// placed bumpers and ramps as one `key value` pair per line. Pipes and available upgrades are rebuilt by the next tick.
+pub fn save_text(state: &GameState) -> String {
+    let mut contents = format!("score {}\n", state.score);
+    for upgrade in &state.upgrades {
+        contents += &format!("upgrade {}\n", upgrade);
+    }
+    for achievement in &state.achievements {
+        contents += &format!("achievement {}\n", achievement_info(*achievement).id);
+    }
+    if state.cut_mode == CutMode::Slice {
+        contents += "cut_mode slice\n";
+    }
+    // Only mixes the player changed, e.g. `emission 0 Red off 1`
+    for entry in state.emission.iter().filter(|e| !e.enabled || e.weight != 1) {
+        let enabled = if entry.enabled { "on" } else { "off" };
+        let color = color_name(entry.color).unwrap_or("Unknown");
+        contents += &format!("emission {} {} {} {}\n", entry.pipe, color, enabled, entry.weight);
+    }
+    // e.g. `segment ramp 100 200 300 250`
+    for segment in &state.placed_segments {
+        let kind = match segment.kind {
+            SegmentKind::Wall => "wall",
+            SegmentKind::Bumper => "bumper",
+            SegmentKind::Ramp => "ramp",
+        };
+        let ((x1, y1), (x2, y2)) = (segment.start, segment.end);
+        contents += &format!("segment {} {} {} {} {}\n", kind, x1, y1, x2, y2);
+    }
+    contents
+}
+
+pub fn load_text(state: &mut GameState, contents: &str) -> Result<(), String> {
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        let (key, value) = line.split_once(' ')
+            .ok_or(format!("Malformed save line '{}'", line))?;
+        match key {
+            "score" => {
+                state.score = Score::from_str(value.trim())
+                    .map_err(|e| format!("Bad score '{}': {}", value, e))?;
+            },
+            "upgrade" => state.upgrades.push(Upgrade::from_str(value)?),
+            "achievement" => match achievement_from_id(value.trim()) {
+                Some(achievement) if !state.achievements.contains(&achievement) => state.achievements.push(achievement),
+                Some(_) => {},
+                None => eprintln!("Skipping unknown achievement '{}' in save", value.trim()),
+            },
+            "cut_mode" => {
+                state.cut_mode = match value.trim() {
+                    "add_side" => CutMode::AddSide,
+                    "slice" => CutMode::Slice,
+                    _ => return Err(format!("Unknown cut mode '{}'", value)),
+                };
+            },
+            "emission" => state.emission.push(parse_emission(value)?),
+            "segment" => state.placed_segments.push(parse_segment(value)?),
+            // Saves from other versions may have keys this one doesn't know
+            _ => eprintln!("Skipping unknown save line '{}'", line),
+        }
+    }
+    Ok(())
+}
+
+fn parse_emission(value: &str) -> Result<ColorEmission, String> {
+    let bad = || format!("Bad emission '{}'", value);
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 4 {
+        return Err(bad());
+    }
+    let weight: u32 = parts[3].parse().map_err(|_| bad())?;
+    Ok(ColorEmission {
+        pipe: parts[0].parse().map_err(|_| bad())?,
+        color: color_from_name(parts[1]).ok_or_else(bad)?,
+        enabled: match parts[2] {
+            "on" => true,
+            "off" => false,
+            _ => return Err(bad()),
+        },
+        weight: weight.clamp(1, MAX_EMISSION_WEIGHT),
+        credit: 0,
+    })
+}
+
+fn parse_segment(value: &str) -> Result<Segment, String> {
+    let bad = || format!("Bad segment '{}'", value);
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 5 {
+        return Err(bad());
+    }
+    let kind = match parts[0] {
+        "wall" => SegmentKind::Wall,
+        "bumper" => SegmentKind::Bumper,
+        "ramp" => SegmentKind::Ramp,
+        _ => return Err(bad()),
+    };
+    let numbers = parts[1..].iter()
+        .map(|p| p.parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<f64>>>()
+        .ok_or_else(bad)?;
+    Ok(Segment { kind, start: (numbers[0], numbers[1]), end: (numbers[2], numbers[3]) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_logic::{achievements::Achievement, state::seeded_state};
+
+    #[test]
+    fn save_keeps_achievements_and_upgrades() {
+        let mut state = seeded_state(1);
+        state.score = Score::from(123_456);
+        state.upgrades = vec![Upgrade::Pipe2, Upgrade::Pipe1Speed(3), Upgrade::ShapeLaser(1)];
+        state.achievements = vec![Achievement::FirstSale, Achievement::LaserPurchased, Achievement::MillionScore];
+        state.cut_mode = CutMode::Slice;
+
+        let mut loaded = seeded_state(1);
+        load_text(&mut loaded, &save_text(&state)).unwrap();
+        assert_eq!(loaded.score, state.score);
+        assert_eq!(loaded.upgrades, state.upgrades);
+        assert_eq!(loaded.achievements, state.achievements);
+        assert_eq!(loaded.cut_mode, CutMode::Slice);
+        assert_eq!(save_text(&loaded), save_text(&state));
+    }
+
+    #[test]
+    fn load_skips_unknown_and_repeated_achievements() {
+        let mut state = seeded_state(1);
+        load_text(&mut state, "achievement first_sale\nachievement no_such_thing\nachievement first_sale\n").unwrap();
+        assert_eq!(state.achievements, vec![Achievement::FirstSale]);
+    }
+}
@@ -0,0 +1,373 @@
+use std::{fs, str::FromStr};
+
+use raylib::{RaylibHandle, consts::{KeyboardKey, MouseButton}, core::{input::key_from_i32, window::WindowState}};
+
+use crate::{
+    game_logic::{action::Action, state::GameState},
+    i18n::{ALL_LANGUAGES, Language, language_from_id, language_id},
+    input::{InputBindings, KeyBinding, REBINDABLE_ACTIONS, default_bindings},
+    layout::Layout,
+};
+
+pub const SETTINGS_PATH: &str = "settings.cfg";
+
+pub const FPS_CAPS: [u32; 6] = [30, 60, 120, 144, 240, 0];
+pub const PARTICLE_DENSITIES: [f32; 4] = [0f32, 0.25, 0.5, 1f32];
+pub const VOLUMES: [f32; 11] = [0f32, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1f32];
+// Seconds, 0 disables autosaving
+pub const AUTOSAVE_INTERVALS: [u64; 4] = [0, 30, 60, 300];
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Notation {
+    // Long names for the score, short suffixes for prices
+    Named,
+    Abbreviated,
+    Scientific,
+}
+
+const ALL_NOTATIONS: [Notation; 3] = [Notation::Named, Notation::Abbreviated, Notation::Scientific];
+
+pub struct Settings {
+    // 0 means unlimited
+    pub fps_cap: u32,
+    pub vsync: bool,
+    pub notation: Notation,
+    pub language: Language,
+    pub particle_density: f32,
+    pub autosave_interval: u64,
+    pub master_volume: f32,
+    pub sound_volume: f32,
+    pub music_volume: f32,
+    pub bindings: InputBindings,
+}
+
+pub fn default_settings() -> Settings {
+    Settings {
+        fps_cap: 60,
+        vsync: false,
+        notation: Notation::Named,
+        language: Language::English,
+        particle_density: 1f32,
+        autosave_interval: 60,
+        master_volume: 0.8,
+        sound_volume: 1f32,
+        music_volume: 0.5,
+        bindings: default_bindings(),
+    }
+}
+
+// Rows of the settings menu, the rebindable actions follow the fixed rows
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SettingsRow {
+    FpsCap,
+    VSync,
+    Notation,
+    Language,
+    ParticleDensity,
+    Autosave,
+    MasterVolume,
+    SoundVolume,
+    MusicVolume,
+    Binding(Action),
+}
+
+const FIXED_ROWS: [SettingsRow; 9] = [
+    SettingsRow::FpsCap,
+    SettingsRow::VSync,
+    SettingsRow::Notation,
+    SettingsRow::Language,
+    SettingsRow::ParticleDensity,
+    SettingsRow::Autosave,
+    SettingsRow::MasterVolume,
+    SettingsRow::SoundVolume,
+    SettingsRow::MusicVolume,
+];
+
+pub fn settings_rows() -> Vec<SettingsRow> {
+    FIXED_ROWS.iter()
+        .copied()
+        .chain(REBINDABLE_ACTIONS.iter().map(|a| SettingsRow::Binding(*a)))
+        .collect()
+}
+
+pub struct SettingsMenu {
+    pub open: bool,
+    pub selected: usize,
+    // Waiting for the next key press to bind to this action
+    pub rebinding: Option<Action>,
+}
+
+pub fn closed_settings_menu() -> SettingsMenu {
+    SettingsMenu { open: false, selected: 0, rebinding: None }
+}
+
+fn save_key_value(key: &str, value: &str) -> String {
+    format!("{} = {}\n", key, value)
+}
+
+pub fn save_settings(settings: &Settings, path: &str) -> Result<(), String> {
+    fs::write(path, settings_text(settings)).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+pub fn settings_text(settings: &Settings) -> String {
+    let notation = match settings.notation {
+        Notation::Named => "named",
+        Notation::Abbreviated => "abbreviated",
+        Notation::Scientific => "scientific",
+    };
+    let mut contents = String::new();
+    contents += &save_key_value("fps_cap", &settings.fps_cap.to_string());
+    contents += &save_key_value("vsync", &settings.vsync.to_string());
+    contents += &save_key_value("notation", notation);
+    contents += &save_key_value("language", language_id(settings.language));
+    contents += &save_key_value("particle_density", &settings.particle_density.to_string());
+    contents += &save_key_value("autosave_interval", &settings.autosave_interval.to_string());
+    contents += &save_key_value("master_volume", &settings.master_volume.to_string());
+    contents += &save_key_value("sound_volume", &settings.sound_volume.to_string());
+    contents += &save_key_value("music_volume", &settings.music_volume.to_string());
+    for binding in &settings.bindings.keys {
+        contents += &save_key_value(&format!("bind {}", binding.action), &(binding.key as i32).to_string());
+    }
+    contents
+}
+
+// Missing files and unknown or malformed lines fall back to the defaults so an
+// old or hand edited config never keeps the game from starting
+pub fn load_settings(path: &str) -> Settings {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse_settings(&contents),
+        Err(_) => default_settings(),
+    }
+}
+
+pub fn parse_settings(contents: &str) -> Settings {
+    let mut settings = default_settings();
+    let mut bindings = vec![];
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        if let Err(e) = load_setting_line(&mut settings, &mut bindings, line) {
+            eprintln!("Ignoring setting '{}': {}", line, e);
+        }
+    }
+    if !bindings.is_empty() {
+        settings.bindings.keys = bindings;
+    }
+    settings
+}
+
+fn load_setting_line(settings: &mut Settings, bindings: &mut Vec<KeyBinding>, line: &str) -> Result<(), String> {
+    let (key, value) = line.split_once('=').ok_or("expected key = value")?;
+    let (key, value) = (key.trim(), value.trim());
+    let bad_value = || format!("bad value '{}'", value);
+    match key {
+        "fps_cap" => settings.fps_cap = value.parse().map_err(|_| bad_value())?,
+        "vsync" => settings.vsync = value.parse().map_err(|_| bad_value())?,
+        "notation" => settings.notation = match value {
+            "named" => Notation::Named,
+            "abbreviated" => Notation::Abbreviated,
+            "scientific" => Notation::Scientific,
+            _ => return Err(format!("unknown notation '{}'", value)),
+        },
+        "language" => settings.language = language_from_id(value)
+            .ok_or(format!("unknown language '{}'", value))?,
+        "particle_density" => {
+            let density: f32 = value.parse().map_err(|_| bad_value())?;
+            settings.particle_density = density.clamp(0f32, 1f32);
+        },
+        "autosave_interval" => settings.autosave_interval = value.parse().map_err(|_| bad_value())?,
+        "master_volume" => settings.master_volume = parse_volume(value).ok_or_else(bad_value)?,
+        "sound_volume" => settings.sound_volume = parse_volume(value).ok_or_else(bad_value)?,
+        "music_volume" => settings.music_volume = parse_volume(value).ok_or_else(bad_value)?,
+        _ => match key.strip_prefix("bind ") {
+            Some(action) => {
+                let action = Action::from_str(action)?;
+                let code: i32 = value.parse().map_err(|_| bad_value())?;
+                let key = key_from_i32(code).ok_or(format!("unknown key code {}", code))?;
+                bindings.push(KeyBinding { key, action });
+            },
+            None => return Err(format!("unknown setting '{}'", key)),
+        },
+    }
+    Ok(())
+}
+
+fn parse_volume(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().map(|v| v.clamp(0f32, 1f32))
+}
+
+pub fn apply_settings(rl: &mut RaylibHandle, settings: &Settings) {
+    rl.set_target_fps(settings.fps_cap);
+    let vsync = WindowState::default().set_vsync_hint(true);
+    if settings.vsync {
+        rl.set_window_state(vsync);
+    } else {
+        rl.clear_window_state(vsync);
+    }
+}
+
+// Top of the first settings row and the height of each row, unscaled
+const MENU_TOP: i32 = 32 + 20 + 32 + 20;
+const MENU_ROW_HEIGHT: i32 = 24;
+
+pub fn settings_row_rect(layout: &Layout, row: usize) -> (i32, i32, i32, i32) {
+    let (x, y, width, _) = layout.playfield;
+    let margin = layout.px(32);
+    (
+        x + margin,
+        y + margin + layout.px(MENU_TOP) + row as i32 * layout.px(MENU_ROW_HEIGHT),
+        width - margin*2,
+        layout.px(MENU_ROW_HEIGHT),
+    )
+}
+
+// Lets the open settings menu take the navigation actions queued by the input
+// layer this frame. Returns true when a setting changed and should be applied.
+pub fn settings_menu_tick(
+    rl: &mut RaylibHandle,
+    menu: &mut SettingsMenu,
+    settings: &mut Settings,
+    layout: &Layout,
+    state: &mut GameState
+) -> bool {
+    if let Some(action) = menu.rebinding {
+        // Whatever was pressed is meant for the binding, not the game
+        state.actions.clear();
+        if let Some(key) = rl.get_key_pressed() {
+            if key != KeyboardKey::KEY_BACKSPACE {
+                settings.bindings.rebind_key(action, key);
+            }
+            menu.rebinding = None;
+            return key != KeyboardKey::KEY_BACKSPACE;
+        }
+        return false;
+    }
+    let mut changed = false;
+    let rows = settings_rows();
+    let mut remaining = std::collections::VecDeque::new();
+    while let Some(action) = state.actions.pop_front() {
+        match action {
+            Action::ToggleSettings => menu.open = !menu.open,
+            Action::SelectNext if menu.open => {
+                menu.selected = (menu.selected + 1).min(rows.len() - 1);
+            },
+            Action::SelectPrevious if menu.open => {
+                menu.selected = menu.selected.saturating_sub(1);
+            },
+            Action::BuySelected if menu.open => {
+                changed |= activate_row(menu, settings, rows[menu.selected], 1);
+            },
+            Action::Adjust(delta) if menu.open => {
+                changed |= activate_row(menu, settings, rows[menu.selected], delta);
+            },
+            action => remaining.push_back(action),
+        }
+    }
+    state.actions = remaining;
+    if menu.open && rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
+        let (x, y) = (rl.get_mouse_x(), rl.get_mouse_y());
+        let clicked = (0..rows.len()).find(|row| {
+            let (row_x, row_y, width, height) = settings_row_rect(layout, *row);
+            (row_x..(row_x+width)).contains(&x) && (row_y..(row_y+height)).contains(&y)
+        });
+        if let Some(row) = clicked {
+            menu.selected = row;
+            changed |= activate_row(menu, settings, rows[row], 1);
+        }
+    }
+    changed
+}
+
+fn cycle<T: PartialEq + Copy>(options: &[T], current: T, delta: i32) -> T {
+    let index = options.iter().position(|o| *o == current).unwrap_or(0) as i32;
+    let len = options.len() as i32;
+    options[(index + delta).rem_euclid(len) as usize]
+}
+
+// Loaded volumes may sit between the menu steps, snap to the nearest one first
+fn cycle_volume(volume: f32, delta: i32) -> f32 {
+    let step = (volume * 10f32).round() as i32;
+    VOLUMES[(step + delta).clamp(0, VOLUMES.len() as i32 - 1) as usize]
+}
+
+fn activate_row(menu: &mut SettingsMenu, settings: &mut Settings, row: SettingsRow, delta: i32) -> bool {
+    match row {
+        SettingsRow::FpsCap => settings.fps_cap = cycle(&FPS_CAPS, settings.fps_cap, delta),
+        SettingsRow::VSync => settings.vsync = !settings.vsync,
+        SettingsRow::Notation => settings.notation = cycle(&ALL_NOTATIONS, settings.notation, delta),
+        SettingsRow::Language => settings.language = cycle(&ALL_LANGUAGES, settings.language, delta),
+        SettingsRow::ParticleDensity => {
+            settings.particle_density = cycle(&PARTICLE_DENSITIES, settings.particle_density, delta);
+        },
+        SettingsRow::Autosave => {
+            settings.autosave_interval = cycle(&AUTOSAVE_INTERVALS, settings.autosave_interval, delta);
+        },
+        SettingsRow::MasterVolume => settings.master_volume = cycle_volume(settings.master_volume, delta),
+        SettingsRow::SoundVolume => settings.sound_volume = cycle_volume(settings.sound_volume, delta),
+        SettingsRow::MusicVolume => settings.music_volume = cycle_volume(settings.music_volume, delta),
+        SettingsRow::Binding(action) => {
+            menu.rebinding = Some(action);
+            return false;
+        },
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let mut settings = default_settings();
+        settings.fps_cap = 144;
+        settings.vsync = true;
+        settings.notation = Notation::Scientific;
+        settings.language = Language::Spanish;
+        settings.particle_density = 0.25;
+        settings.autosave_interval = 300;
+        settings.music_volume = 0.3;
+        settings.bindings.rebind_key(Action::TogglePause, KeyboardKey::KEY_F1);
+
+        let loaded = parse_settings(&settings_text(&settings));
+        assert_eq!(settings_text(&loaded), settings_text(&settings));
+        assert_eq!(loaded.notation, Notation::Scientific);
+        assert_eq!(loaded.language, Language::Spanish);
+    }
+
+    #[test]
+    fn bad_and_unknown_lines_keep_the_defaults() {
+        let settings = parse_settings(
+            "fps_cap = fast\n\
+             vsync\n\
+             notation = roman\n\
+             language = xx\n\
+             colour = blue\n\
+             bind Jump = 32\n\
+             bind TogglePause = 99999\n\
+             autosave_interval = 30\n",
+        );
+        let defaults = default_settings();
+        assert_eq!(settings.fps_cap, defaults.fps_cap);
+        assert_eq!(settings.vsync, defaults.vsync);
+        assert_eq!(settings.notation, defaults.notation);
+        assert_eq!(settings.language, defaults.language);
+        assert_eq!(settings.bindings.keys.len(), defaults.bindings.keys.len());
+        // Good lines after bad ones still apply
+        assert_eq!(settings.autosave_interval, 30);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let settings = parse_settings("particle_density = 3\nmaster_volume = -1\nsound_volume = 0.5\n");
+        assert_eq!(settings.particle_density, 1f32);
+        assert_eq!(settings.master_volume, 0f32);
+        assert_eq!(settings.sound_volume, 0.5);
+    }
+
+    #[test]
+    fn saved_bindings_replace_the_defaults() {
+        let settings = parse_settings(&format!("bind BuySelected = {}\n", KeyboardKey::KEY_B as i32));
+        assert_eq!(settings.bindings.keys.len(), 1);
+        assert_eq!(settings.bindings.keys[0].key as i32, KeyboardKey::KEY_B as i32);
+        assert_eq!(settings.bindings.keys[0].action, Action::BuySelected);
+    }
+}